@@ -1,6 +1,126 @@
-use crate::math::vec3::Vec3;
+use crate::{math::vec3::Vec3, render::ray::Ray};
 
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AABB {
-    tmax: Vec3,
-    tmin: Vec3,
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for AABB {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl AABB {
+    /// The box spanned by two opposite corners, in any order.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    /// A box containing nothing, the identity of `union`.
+    pub fn empty() -> Self {
+        Self {
+            min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Whether the box is bounded, which shapes like infinite planes are not.
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|i| self.min[i].is_finite() && self.max[i].is_finite())
+    }
+
+    pub fn union(&self, other: &AABB) -> AABB {
+        Self {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    /// The box contained in both boxes, empty if they do not overlap.
+    pub fn intersection(&self, other: &AABB) -> AABB {
+        Self {
+            min: Vec3::new(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        }
+    }
+
+    pub fn union_point(&self, p: &Vec3) -> AABB {
+        self.union(&AABB { min: *p, max: *p })
+    }
+
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn contains(&self, p: &Vec3) -> bool {
+        (0..3).all(|i| self.min[i] <= p[i] && p[i] <= self.max[i])
+    }
+
+    /// Position of `p` relative to the box, `(0, 0, 0)` at `min` and
+    /// `(1, 1, 1)` at `max`.
+    pub fn offset(&self, p: &Vec3) -> Vec3 {
+        let mut o = *p - self.min;
+        for i in 0..3 {
+            if self.max[i] > self.min[i] {
+                o[i] /= self.max[i] - self.min[i];
+            }
+        }
+        o
+    }
+
+    /// The parametric range `[t0, t1]` over which `ray` is inside the box,
+    /// clipped to `[0, t_max]` (slab method).
+    pub fn intersect(&self, ray: &Ray, t_max: f64) -> Option<(f64, f64)> {
+        let mut t0: f64 = 0.0;
+        let mut t1 = t_max;
+        for i in 0..3 {
+            let inv_d = 1.0 / ray.d[i];
+            let mut t_near = (self.min[i] - ray.p[i]) * inv_d;
+            let mut t_far = (self.max[i] - ray.p[i]) * inv_d;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // NaN when the ray lies in a slab plane, keep the current range.
+            if t_near > t0 {
+                t0 = t_near;
+            }
+            if t_far < t1 {
+                t1 = t_far;
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+        Some((t0, t1))
+    }
 }
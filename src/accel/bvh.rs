@@ -23,7 +23,7 @@ impl<'a> BVH<'a> {
         }
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Option<HitRecord> {
+    pub fn intersect(&self, ray: &Ray) -> Option<HitRecord<'_>> {
         todo!()
     }
 }
@@ -1,3 +1,4 @@
 pub mod aabb;
 pub mod bvh;
 pub mod bvh_node;
+pub mod octree;
@@ -0,0 +1,133 @@
+use crate::{accel::aabb::AABB, math::vec3::Vec3};
+
+/// Deepest level items are pushed down to.
+const MAX_DEPTH: usize = 16;
+
+#[derive(Default)]
+struct OctreeNode<T> {
+    items: Vec<T>,
+    children: Option<Box<[OctreeNode<T>; 8]>>,
+}
+
+/// Octree storing items that cover a region of space, for looking up the
+/// items overlapping a point. An item is stored in every node it overlaps
+/// at the level where the nodes are about its size.
+pub struct Octree<T> {
+    bounds: AABB,
+    root: OctreeNode<T>,
+}
+
+/// Bounds of the `child`-th octant of `bounds`, the bits of `child` select
+/// the upper half along x, y and z.
+fn octant(bounds: &AABB, child: usize) -> AABB {
+    let center = bounds.center();
+    let mut min = bounds.min;
+    let mut max = center;
+    for axis in 0..3 {
+        if child & (1 << axis) != 0 {
+            min[axis] = center[axis];
+            max[axis] = bounds.max[axis];
+        }
+    }
+    AABB { min, max }
+}
+
+fn overlaps(a: &AABB, b: &AABB) -> bool {
+    (0..3).all(|i| a.min[i] <= b.max[i] && b.min[i] <= a.max[i])
+}
+
+impl<T: Clone + Default> Octree<T> {
+    pub fn new(bounds: AABB) -> Self {
+        Self {
+            bounds,
+            root: OctreeNode::default(),
+        }
+    }
+
+    /// Store `item`, which covers `item_bounds`.
+    pub fn add(&mut self, item: T, item_bounds: &AABB) {
+        let mut stack = vec![(&mut self.root, self.bounds, 0)];
+        while let Some((node, bounds, depth)) = stack.pop() {
+            if depth == MAX_DEPTH
+                || bounds.diagonal().length_squared() < item_bounds.diagonal().length_squared()
+            {
+                node.items.push(item.clone());
+                continue;
+            }
+            let children = node.children.get_or_insert_with(Default::default);
+            for (child, node) in children.iter_mut().enumerate() {
+                let child_bounds = octant(&bounds, child);
+                if overlaps(&child_bounds, item_bounds) {
+                    stack.push((node, child_bounds, depth + 1));
+                }
+            }
+        }
+    }
+
+    /// Call `f` with every item stored in the nodes containing `p`. Items
+    /// overlapping `p` are all visited, others may be too.
+    pub fn lookup(&self, p: &Vec3, mut f: impl FnMut(&T)) {
+        if !self.bounds.contains(p) {
+            return;
+        }
+        let mut node = &self.root;
+        let mut bounds = self.bounds;
+        loop {
+            node.items.iter().for_each(&mut f);
+            let Some(children) = &node.children else {
+                return;
+            };
+            let center = bounds.center();
+            let child = (0..3).fold(0, |child, axis| {
+                child | (usize::from(p[axis] > center[axis]) << axis)
+            });
+            bounds = octant(&bounds, child);
+            node = &children[child];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items_at(octree: &Octree<usize>, p: &Vec3) -> Vec<usize> {
+        let mut items = Vec::new();
+        octree.lookup(p, |&item| items.push(item));
+        items.sort();
+        items.dedup();
+        items
+    }
+
+    #[test]
+    fn test_lookup_visits_overlapping_items() {
+        let mut octree = Octree::new(AABB::new(
+            Vec3::new(-4.0, -4.0, -4.0),
+            Vec3::new(4.0, 4.0, 4.0),
+        ));
+        let extent = Vec3::new(0.1, 0.1, 0.1);
+        let centers = [
+            Vec3::new(-2.0, -2.0, -2.0),
+            Vec3::new(2.0, 1.0, -3.0),
+            Vec3::new(0.5, 3.0, 3.0),
+        ];
+        for (item, c) in centers.iter().enumerate() {
+            octree.add(item, &AABB::new(*c - extent, *c + extent));
+        }
+        // An item straddling the centre is stored in all the octants.
+        octree.add(
+            3,
+            &AABB::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)),
+        );
+
+        for (item, c) in centers.iter().enumerate() {
+            assert_eq!(
+                items_at(&octree, &(*c + Vec3::new(0.05, -0.05, 0.0))),
+                vec![item]
+            );
+        }
+        assert_eq!(items_at(&octree, &Vec3::new(0.5, -0.5, 0.5)), vec![3]);
+        assert!(items_at(&octree, &Vec3::new(-3.0, 3.0, 3.0)).is_empty());
+        assert!(items_at(&octree, &Vec3::new(5.0, 0.0, 0.0)).is_empty());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod options;
 pub mod rng;
+pub mod sampler;
@@ -1,4 +1,6 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+use crate::integrator::{debug::DebugMode, direct::LightStrategy, gradient::Reconstruction};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
@@ -6,7 +8,7 @@ pub struct Options {
     #[arg(short, long, value_parser = parse_resolution)]
     pub resolution: (usize, usize),
 
-    #[arg(short = 'r', long, default_value_t = 5)]
+    #[arg(short = 'd', long, default_value_t = 5)]
     pub recursion_depth: usize,
 
     #[arg(short = 'o', long, default_value_t = String::from("output.ppm"))]
@@ -14,6 +16,147 @@ pub struct Options {
 
     #[arg(short = 'f', long = "focal")]
     pub focal_length: f64,
+
+    /// Number of samples taken per pixel.
+    #[arg(short = 's', long, default_value_t = 16)]
+    pub spp: usize,
+
+    /// Number of bounces after which paths may be terminated by Russian roulette.
+    #[arg(long, default_value_t = 3)]
+    pub rr_depth: usize,
+
+    #[arg(short = 'i', long, value_enum, default_value_t = IntegratorKind::Path)]
+    pub integrator: IntegratorKind,
+
+    /// Lights sampled at each hit by the direct lighting integrator.
+    #[arg(long, value_enum, default_value_t = LightStrategy::All)]
+    pub light_strategy: LightStrategy,
+
+    /// Also write the contribution of every BDPT strategy to its own image.
+    #[arg(long)]
+    pub bdpt_strategies: bool,
+
+    /// Number of camera and photon passes of the SPPM integrator.
+    #[arg(long, default_value_t = 16)]
+    pub sppm_iterations: usize,
+
+    /// Number of photons traced per SPPM pass.
+    #[arg(long, default_value_t = 100_000)]
+    pub photons: usize,
+
+    /// Initial photon gather radius of the SPPM integrator.
+    #[arg(long, default_value_t = 0.25)]
+    pub sppm_radius: f64,
+
+    /// Number of bootstrap samples used to seed the PSSMLT chains.
+    #[arg(long, default_value_t = 100_000)]
+    pub mlt_bootstrap: usize,
+
+    /// Number of independent PSSMLT Markov chains.
+    #[arg(long, default_value_t = 1000)]
+    pub mlt_chains: usize,
+
+    /// Number of PSSMLT mutations per pixel.
+    #[arg(long, default_value_t = 100)]
+    pub mlt_mutations: usize,
+
+    /// Standard deviation of the PSSMLT small step mutations.
+    #[arg(long, default_value_t = 0.01)]
+    pub mlt_sigma: f64,
+
+    /// Probability of a PSSMLT large step mutation.
+    #[arg(long, default_value_t = 0.3)]
+    pub mlt_large_step: f64,
+
+    /// Distance beyond which geometry does not occlude in AO renders.
+    #[arg(long, default_value_t = f64::INFINITY)]
+    pub ao_distance: f64,
+
+    /// Number of occlusion rays per camera ray in AO renders.
+    #[arg(long, default_value_t = 4)]
+    pub ao_samples: usize,
+
+    /// Attribute shown by the debug integrator.
+    #[arg(long, value_enum, default_value_t = DebugMode::Normal)]
+    pub debug_mode: DebugMode,
+
+    /// Guide the path tracer with radiance learned over training passes.
+    #[arg(long)]
+    pub guiding: bool,
+
+    /// Number of path guiding training passes, pass `i` takes `2^i` samples
+    /// per pixel.
+    #[arg(long, default_value_t = 5)]
+    pub guiding_iterations: usize,
+
+    /// Memory budget of the path guiding structure, in megabytes.
+    #[arg(long, default_value_t = 64)]
+    pub guiding_memory: usize,
+
+    /// Largest interpolation error of the irradiance cache, smaller values
+    /// compute more records.
+    #[arg(long, default_value_t = 0.2)]
+    pub ic_accuracy: f64,
+
+    /// Number of hemisphere rays traced for an irradiance cache record.
+    #[arg(long, default_value_t = 256)]
+    pub ic_samples: usize,
+
+    /// Smallest distance to the geometry an irradiance record accounts for.
+    #[arg(long, default_value_t = 0.05)]
+    pub ic_min_spacing: f64,
+
+    /// Largest distance to the geometry an irradiance record accounts for.
+    #[arg(long, default_value_t = 5.0)]
+    pub ic_max_spacing: f64,
+
+    /// File the irradiance cache is loaded from, if it exists, and saved to.
+    #[arg(long)]
+    pub ic_file: Option<String>,
+
+    /// Norm of the gradient-domain reconstruction.
+    #[arg(long, value_enum, default_value_t = Reconstruction::L1)]
+    pub gpt_reconstruction: Reconstruction,
+
+    /// Weight of the primal image in the gradient-domain reconstruction.
+    #[arg(long, default_value_t = 0.2)]
+    pub gpt_alpha: f64,
+
+    /// Number of light paths depositing virtual point lights.
+    #[arg(long, default_value_t = 64)]
+    pub vpl_paths: usize,
+
+    /// Distances to virtual point lights below this are clamped.
+    #[arg(long, default_value_t = 0.2)]
+    pub vpl_min_distance: f64,
+
+    /// Scattering coefficient of a homogeneous fog filling the scene, only
+    /// rendered by the volumetric path tracer.
+    #[arg(long, default_value_t = 0.0)]
+    pub fog: f64,
+
+    /// Seed of the random number generators.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+/// Light transport algorithms selectable from the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IntegratorKind {
+    Ao,
+    Bdpt,
+    Debug,
+    Direct,
+    EyeLight,
+    Gpt,
+    IrradianceCache,
+    LightTracing,
+    Path,
+    Pssmlt,
+    Sppm,
+    VolPath,
+    Vpl,
+    Whitted,
 }
 
 fn parse_resolution(s: &str) -> Result<(usize, usize), String> {
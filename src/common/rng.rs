@@ -1,17 +1,64 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+const PCG32_DEFAULT_STATE: u64 = 0x853c49e6748fea9b;
+const PCG32_DEFAULT_STREAM: u64 = 0xda3e39cb94b95bdb;
+const PCG32_MULT: u64 = 0x5851f42d4c957f2d;
 
-static SEED_COUNTER: AtomicU64 = AtomicU64::new(42);
+/// PCG32 random number generator (O'Neill 2014). Every generator owns its
+/// state, so a sequence of numbers can be replayed by seeding a new
+/// generator the same way.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+    inc: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self {
+            state: PCG32_DEFAULT_STATE,
+            inc: PCG32_DEFAULT_STREAM,
+        }
+    }
+}
+
+impl Rng {
+    /// A generator on stream `sequence_index`, seeded from the index.
+    pub fn new(sequence_index: u64) -> Self {
+        let mut rng = Self::default();
+        rng.set_sequence(sequence_index, mix_bits(sequence_index));
+        rng
+    }
 
-pub fn random_f64_lockfree() -> f64 {
-    // Simple LCG (Linear Congruential Generator)
-    // not cryptographically secure but very fast for non-security applications
-    let prev = SEED_COUNTER.load(Ordering::Relaxed);
-    let mut next = prev.wrapping_mul(1664525).wrapping_add(1013904223);
-    if next == 0 {
-        next += 1013904223;
+    /// Restart the generator on stream `sequence_index` with `seed`.
+    pub fn set_sequence(&mut self, sequence_index: u64, seed: u64) {
+        self.state = 0;
+        self.inc = (sequence_index << 1) | 1;
+        self.uniform_u32();
+        self.state = self.state.wrapping_add(seed);
+        self.uniform_u32();
     }
-    SEED_COUNTER.store(next, Ordering::Relaxed);
 
-    // Convert to f64 in range [0, 1)
-    (next as f64) / (u64::MAX as f64)
+    pub fn uniform_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(PCG32_MULT).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    /// A uniform number in `[0, 1)` with 53 bits of precision.
+    pub fn uniform_f64(&mut self) -> f64 {
+        let bits = (((self.uniform_u32() as u64) << 32) | self.uniform_u32() as u64) >> 11;
+        bits as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Scramble the bits of `v`, used to derive well distributed seeds from
+/// small integers.
+pub fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
 }
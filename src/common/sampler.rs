@@ -0,0 +1,199 @@
+use std::f64::consts::PI;
+
+use super::rng::{Rng, mix_bits};
+
+/// The largest `f64` strictly below one.
+pub const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+/// Source of the uniform random numbers that drive an integrator.
+pub trait Sampler {
+    /// Prepare the sampler for the `index`-th sample of `pixel`.
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    /// A sample in `[0, 1)`.
+    fn get_1d(&mut self) -> f64;
+
+    /// A sample in `[0, 1)^2`.
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Uniform, uncorrelated samples. The sequence of every pixel sample only
+/// depends on the pixel, the sample index and the seed, so any sample can be
+/// replayed.
+#[derive(Default)]
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Rng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        let pixel_hash = mix_bits(((pixel.0 as u64) << 32) ^ pixel.1 as u64 ^ mix_bits(self.seed));
+        self.rng.set_sequence(pixel_hash, mix_bits(index as u64));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.uniform_f64().min(ONE_MINUS_EPSILON)
+    }
+}
+
+/// A coordinate of the primary sample space, with the state needed to undo
+/// a rejected mutation.
+#[derive(Clone, Copy, Debug, Default)]
+struct PrimarySample {
+    value: f64,
+    /// Iteration at which `value` was last mutated.
+    last_modification_iteration: usize,
+    value_backup: f64,
+    modify_backup: usize,
+}
+
+impl PrimarySample {
+    fn backup(&mut self) {
+        self.value_backup = self.value;
+        self.modify_backup = self.last_modification_iteration;
+    }
+
+    fn restore(&mut self) {
+        self.value = self.value_backup;
+        self.last_modification_iteration = self.modify_backup;
+    }
+}
+
+/// Sampler for primary sample space Metropolis light transport (Kelemen et
+/// al. 2002). The samples it hands out form a point in the primary sample
+/// space that is mutated at every iteration, either by a small gaussian
+/// perturbation or by a large step that draws fresh uniform numbers.
+///
+/// Coordinates are mutated lazily when they are requested, so paths of
+/// different lengths can share the same state. A sampler created with the
+/// same seed replays the same initial sample.
+pub struct MltSampler {
+    rng: Rng,
+    /// Standard deviation of the small step perturbation.
+    sigma: f64,
+    large_step_probability: f64,
+    samples: Vec<PrimarySample>,
+    current_iteration: usize,
+    large_step: bool,
+    last_large_step_iteration: usize,
+    sample_index: usize,
+}
+
+impl MltSampler {
+    pub fn new(seed: u64, sigma: f64, large_step_probability: f64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            sigma,
+            large_step_probability,
+            samples: Vec::new(),
+            current_iteration: 0,
+            large_step: true,
+            last_large_step_iteration: 0,
+            sample_index: 0,
+        }
+    }
+
+    /// Propose a new mutation of the current sample.
+    pub fn start_iteration(&mut self) {
+        self.current_iteration += 1;
+        self.large_step = self.rng.uniform_f64() < self.large_step_probability;
+        self.sample_index = 0;
+    }
+
+    /// Keep the proposed sample.
+    pub fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step_iteration = self.current_iteration;
+        }
+    }
+
+    /// Go back to the sample before the last mutation.
+    pub fn reject(&mut self) {
+        for sample in &mut self.samples {
+            if sample.last_modification_iteration == self.current_iteration {
+                sample.restore();
+            }
+        }
+        self.current_iteration -= 1;
+    }
+
+    /// Bring the coordinate at `index` up to date with the current iteration.
+    fn ensure_ready(&mut self, index: usize) {
+        if index >= self.samples.len() {
+            self.samples.resize(index + 1, PrimarySample::default());
+        }
+        let sample = &mut self.samples[index];
+
+        // Catch up with a large step accepted since the last access.
+        if sample.last_modification_iteration < self.last_large_step_iteration {
+            sample.value = self.rng.uniform_f64();
+            sample.last_modification_iteration = self.last_large_step_iteration;
+        }
+
+        sample.backup();
+        if self.large_step {
+            sample.value = self.rng.uniform_f64();
+        } else {
+            // Apply all the small steps missed since the last access at once.
+            let n_small = (self.current_iteration - sample.last_modification_iteration) as f64;
+            let (u1, u2) = (self.rng.uniform_f64(), self.rng.uniform_f64());
+            let normal = (-2.0 * (1.0 - u1).ln()).sqrt() * (2.0 * PI * u2).cos();
+            sample.value += normal * self.sigma * n_small.sqrt();
+            sample.value -= sample.value.floor();
+        }
+        sample.value = sample.value.min(ONE_MINUS_EPSILON);
+        sample.last_modification_iteration = self.current_iteration;
+    }
+}
+
+impl Sampler for MltSampler {
+    /// Samples are addressed by the mutation, not by the pixel.
+    fn start_pixel_sample(&mut self, _pixel: (usize, usize), _index: usize) {}
+
+    fn get_1d(&mut self) -> f64 {
+        let index = self.sample_index;
+        self.sample_index += 1;
+        self.ensure_ready(index);
+        self.samples[index].value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mlt_sampler_replays_and_restores() {
+        let draw = |sampler: &mut MltSampler| (0..8).map(|_| sampler.get_1d()).collect::<Vec<_>>();
+
+        let mut sampler = MltSampler::new(7, 0.01, 0.3);
+        let initial = draw(&mut sampler);
+        assert_eq!(initial, draw(&mut MltSampler::new(7, 0.01, 0.3)));
+
+        for _ in 0..16 {
+            sampler.start_iteration();
+            let proposed = draw(&mut sampler);
+            assert!(proposed.iter().all(|&u| (0.0..1.0).contains(&u)));
+            sampler.reject();
+        }
+        assert!(
+            sampler
+                .samples
+                .iter()
+                .zip(&initial)
+                .all(|(s, &u)| s.value == u)
+        );
+    }
+}
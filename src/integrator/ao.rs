@@ -0,0 +1,82 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::Integrator,
+    math::sampling::cosine_sample_hemisphere,
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+};
+
+/// Ambient occlusion: the fraction of the cosine weighted hemisphere above
+/// each first hit that is not blocked within `max_distance`. Ignores
+/// materials and lights, so it works as a clay render of the geometry.
+pub struct AoIntegrator {
+    /// Occluders further away than this do not count.
+    max_distance: f64,
+    /// Number of occlusion rays cast per camera ray.
+    samples: usize,
+}
+
+impl AoIntegrator {
+    pub fn new(max_distance: f64, samples: usize) -> Self {
+        Self {
+            max_distance,
+            samples,
+        }
+    }
+}
+
+impl Integrator for AoIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        let Some(hit) = scene.find_first_hit(ray) else {
+            return Colour::new();
+        };
+        if self.samples == 0 {
+            return Colour::new();
+        }
+        let wo = -ray.d.normalize();
+        let n = if hit.n.dot(&wo) < 0.0 { -hit.n } else { hit.n };
+
+        // With cosine distributed directions the estimator reduces to the
+        // fraction of unoccluded rays.
+        let unoccluded = (0..self.samples)
+            .filter(|_| {
+                let wi = n.from_local(&cosine_sample_hemisphere(sampler.get_2d()));
+                scene
+                    .find_first_hit(&hit.spawn_ray(&wi))
+                    .is_none_or(|occluder| occluder.t >= self.max_distance)
+            })
+            .count();
+        Colour::grey(unoccluded as f64 / self.samples as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        material::Material,
+        math::{matrix4::Matrix4, transform::Transform, vec3::Vec3},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    #[test]
+    fn test_unoccluded_plane_is_white() {
+        // A large sphere standing in for a plane, with nothing above it.
+        let mut scene = Scene::new();
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, -1000.0])),
+            Arc::new(Material::diffuse(Colour::grey(0.5))),
+        ));
+
+        let integrator = AoIntegrator::new(10.0, 64);
+        let mut sampler = IndependentSampler::new(0);
+        for (x, y) in [(0.0, 0.0), (3.0, -2.0), (-5.0, 7.0)] {
+            let ray = Ray::new(Vec3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+            assert_eq!(integrator.li(&ray, &scene, &mut sampler), Colour::grey(1.0));
+        }
+    }
+}
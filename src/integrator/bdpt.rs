@@ -0,0 +1,765 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, pick_light, shading_normal_correction},
+    light::Light,
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, film::Film, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::{HitRecord, offset_origin},
+};
+
+/// The quantity a subpath carries: radiance from the camera subpath's point
+/// of view, importance from the light subpath's.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TransportMode {
+    Radiance,
+    Importance,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// A vertex of a camera or light subpath. Densities are stored with respect
+/// to surface area so that they can be compared across strategies.
+#[derive(Clone)]
+struct Vertex<'a> {
+    kind: VertexKind,
+    p: Vec3,
+    /// Surface normal, zero for the eye and for point lights.
+    n: Vec3,
+    /// Shading normal, `n` except at surface hits with shading normals.
+    ns: Vec3,
+    /// Unit direction towards the previous vertex of the subpath.
+    wo: Vec3,
+    /// Throughput of the subpath up to and including this vertex.
+    beta: Colour,
+    /// Whether the path was scattered by a delta lobe at this vertex.
+    delta: bool,
+    /// Density of sampling this vertex from the previous one.
+    pdf_fwd: f64,
+    /// Density of sampling this vertex from the next one, had the subpath
+    /// been generated in the opposite direction.
+    pdf_rev: f64,
+    hit: Option<HitRecord<'a>>,
+    light: Option<&'a dyn Light>,
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(p: Vec3, beta: Colour) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            p,
+            n: Vec3::zero(),
+            ns: Vec3::zero(),
+            wo: Vec3::zero(),
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            hit: None,
+            light: None,
+        }
+    }
+
+    fn light(light: &'a dyn Light, p: Vec3, n: Vec3, beta: Colour, pdf_fwd: f64) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            p,
+            n,
+            ns: n,
+            wo: Vec3::zero(),
+            beta,
+            delta: false,
+            pdf_fwd,
+            pdf_rev: 0.0,
+            hit: None,
+            light: Some(light),
+        }
+    }
+
+    fn surface(hit: HitRecord<'a>, wo: Vec3, beta: Colour) -> Self {
+        Self {
+            kind: VertexKind::Surface,
+            p: hit.p,
+            n: hit.n,
+            ns: hit.ns,
+            wo,
+            beta,
+            delta: false,
+            pdf_fwd: 0.0,
+            pdf_rev: 0.0,
+            hit: Some(hit),
+            light: None,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        self.n != Vec3::zero()
+    }
+
+    /// The light emitting at this vertex, if any.
+    fn as_light(&self) -> Option<&'a dyn Light> {
+        match self.kind {
+            VertexKind::Light => self.light,
+            VertexKind::Surface => self
+                .hit
+                .and_then(|hit| hit.area_light)
+                .map(|light| light as &dyn Light),
+            VertexKind::Camera => None,
+        }
+    }
+
+    fn is_delta_light(&self) -> bool {
+        self.kind == VertexKind::Light && self.light.is_some_and(|light| light.is_delta())
+    }
+
+    /// Whether a deterministic connection to this vertex can carry light.
+    fn is_connectible(&self) -> bool {
+        match self.kind {
+            VertexKind::Camera | VertexKind::Light => true,
+            VertexKind::Surface => self.hit.is_some_and(|hit| !hit.material.is_delta()),
+        }
+    }
+
+    /// BSDF value for light scattered between `next` and the previous vertex,
+    /// corrected for shading normals on light subpaths.
+    fn f(&self, next: &Vertex, mode: TransportMode) -> Colour {
+        let Some(hit) = &self.hit else {
+            return Colour::new();
+        };
+        let wi = (next.p - self.p).normalize_or_zero();
+        let f = hit.material.eval(hit, &self.wo, &wi);
+        match mode {
+            TransportMode::Radiance => f,
+            TransportMode::Importance => f * shading_normal_correction(hit, &self.wo, &wi),
+        }
+    }
+
+    /// Radiance emitted from this vertex towards `v`.
+    fn le(&self, v: &Vertex) -> Colour {
+        let Some(hit) = &self.hit else {
+            return Colour::new();
+        };
+        let w = (v.p - self.p).normalize_or_zero();
+        hit.material.emitted(hit, &w)
+    }
+
+    /// Convert a solid angle density at this vertex into an area density at
+    /// `next`.
+    fn convert_density(&self, pdf: f64, next: &Vertex) -> f64 {
+        let w = next.p - self.p;
+        let dist_squared = w.length_squared();
+        if dist_squared == 0.0 {
+            return 0.0;
+        }
+        let inv_dist_squared = 1.0 / dist_squared;
+        let mut pdf = pdf * inv_dist_squared;
+        if next.is_on_surface() {
+            pdf *= next.n.dot(&(w * inv_dist_squared.sqrt())).abs();
+        }
+        pdf
+    }
+
+    /// Area density of sampling `next` from this vertex, given that the path
+    /// arrived from `prev`.
+    fn pdf(&self, camera: Option<&Camera>, prev: Option<&Vertex>, next: &Vertex) -> f64 {
+        if self.kind == VertexKind::Light {
+            return self.pdf_light(next);
+        }
+        let wn = (next.p - self.p).normalize_or_zero();
+        if wn == Vec3::zero() {
+            return 0.0;
+        }
+        let pdf = match (self.kind, &self.hit, prev) {
+            (VertexKind::Camera, _, _) => camera.map_or(0.0, |camera| camera.pdf_we(&wn).1),
+            (VertexKind::Surface, Some(hit), Some(prev)) => {
+                let wp = (prev.p - self.p).normalize_or_zero();
+                hit.material.pdf(hit, &wp, &wn)
+            }
+            _ => 0.0,
+        };
+        self.convert_density(pdf, next)
+    }
+
+    /// Area density of this light vertex emitting towards `next`.
+    fn pdf_light(&self, next: &Vertex) -> f64 {
+        let Some(light) = self.as_light() else {
+            return 0.0;
+        };
+        let w = next.p - self.p;
+        let dist_squared = w.length_squared();
+        if dist_squared == 0.0 {
+            return 0.0;
+        }
+        let w = w / dist_squared.sqrt();
+        let (_, pdf_dir) = light.pdf_le(&Ray::new(self.p, w), &self.n);
+        let mut pdf = pdf_dir / dist_squared;
+        if next.is_on_surface() {
+            pdf *= next.n.dot(&w).abs();
+        }
+        pdf
+    }
+
+    /// Area density of picking this point when starting a light subpath.
+    fn pdf_light_origin(&self, n_lights: usize, next: &Vertex) -> f64 {
+        let Some(light) = self.as_light() else {
+            return 0.0;
+        };
+        let w = (next.p - self.p).normalize_or_zero();
+        let (pdf_pos, _) = light.pdf_le(&Ray::new(self.p, w), &self.n);
+        pdf_pos / n_lights as f64
+    }
+}
+
+/// Weighted contribution of a single `(s, t)` strategy, with the raster
+/// position it was splatted to when it connected to the eye.
+struct StrategySample {
+    s: usize,
+    t: usize,
+    contribution: Colour,
+    raster: Option<(f64, f64)>,
+}
+
+/// Bidirectional path tracer that connects every prefix of a camera subpath
+/// to every prefix of a light subpath and weights the strategies with
+/// multiple importance sampling.
+pub struct BdptIntegrator {
+    /// Maximum number of bounces of a full path.
+    max_depth: usize,
+    /// Write the weighted contribution of every `(s, t)` strategy to its own
+    /// image next to the output.
+    visualize_strategies: bool,
+}
+
+impl BdptIntegrator {
+    pub fn new(max_depth: usize, visualize_strategies: bool) -> Self {
+        Self {
+            max_depth,
+            visualize_strategies,
+        }
+    }
+
+    /// Estimate the radiance along the camera ray `ray`. Light subpaths that
+    /// connect directly to the eye are returned as splats; they are only
+    /// considered when `camera` is given. `strategies` collects the weighted
+    /// contribution of every strategy, with splats marked by their raster.
+    fn sample(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: Option<&Camera>,
+        sampler: &mut dyn Sampler,
+        splats: &mut Vec<((f64, f64), Colour)>,
+        mut strategies: Option<&mut Vec<StrategySample>>,
+    ) -> Colour {
+        let mut camera_vertices = Vec::with_capacity(self.max_depth + 2);
+        let mut light_vertices = Vec::with_capacity(self.max_depth + 1);
+        self.generate_camera_subpath(ray, scene, camera, sampler, &mut camera_vertices);
+        self.generate_light_subpath(scene, sampler, &mut light_vertices);
+
+        let mut l = Colour::new();
+        for t in 1..=camera_vertices.len() {
+            for s in 0..=light_vertices.len() {
+                let depth = s as isize + t as isize - 2;
+                if (s == 1 && t == 1) || depth < 0 || depth > self.max_depth as isize {
+                    continue;
+                }
+                if t == 1 && camera.is_none() {
+                    continue;
+                }
+                let (contribution, raster) = connect(
+                    scene,
+                    camera,
+                    &light_vertices,
+                    &camera_vertices,
+                    s,
+                    t,
+                    sampler,
+                );
+                if contribution.has_nans() || contribution.is_black() {
+                    continue;
+                }
+                if let Some(strategies) = strategies.as_deref_mut() {
+                    strategies.push(StrategySample {
+                        s,
+                        t,
+                        contribution,
+                        raster,
+                    });
+                }
+                match raster {
+                    Some(raster) => splats.push((raster, contribution)),
+                    None => l += contribution,
+                }
+            }
+        }
+        l
+    }
+
+    fn generate_camera_subpath<'a>(
+        &self,
+        ray: &Ray,
+        scene: &'a Scene,
+        camera: Option<&Camera>,
+        sampler: &mut dyn Sampler,
+        path: &mut Vec<Vertex<'a>>,
+    ) {
+        let beta = Colour::grey(1.0);
+        path.push(Vertex::camera(ray.p, beta));
+        let pdf_dir = camera.map_or(0.0, |camera| camera.pdf_we(&ray.d).1);
+        random_walk(
+            scene,
+            *ray,
+            sampler,
+            beta,
+            pdf_dir,
+            self.max_depth + 1,
+            TransportMode::Radiance,
+            path,
+        );
+    }
+
+    fn generate_light_subpath<'a>(
+        &self,
+        scene: &'a Scene,
+        sampler: &mut dyn Sampler,
+        path: &mut Vec<Vertex<'a>>,
+    ) {
+        let Some((light, light_pdf)) = pick_light(scene, sampler.get_1d()) else {
+            return;
+        };
+        let Some(emission) = light.sample_le(sampler.get_2d(), sampler.get_2d()) else {
+            return;
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 || emission.le.is_black() {
+            return;
+        }
+        path.push(Vertex::light(
+            light,
+            emission.ray.p,
+            emission.n,
+            emission.le,
+            emission.pdf_pos * light_pdf,
+        ));
+        let cos_theta = if emission.n == Vec3::zero() {
+            1.0
+        } else {
+            emission.n.dot(&emission.ray.d.normalize()).abs()
+        };
+        let beta = emission.le * cos_theta / (light_pdf * emission.pdf_pos * emission.pdf_dir);
+        random_walk(
+            scene,
+            emission.ray,
+            sampler,
+            beta,
+            emission.pdf_dir,
+            self.max_depth,
+            TransportMode::Importance,
+            path,
+        );
+    }
+}
+
+impl Integrator for BdptIntegrator {
+    /// Radiance along `ray` from every strategy that does not connect a
+    /// light subpath directly to the eye.
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.sample(ray, scene, None, sampler, &mut Vec::new(), None)
+    }
+
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let resolution = camera.film().resolution();
+
+        // One film per strategy with at least one camera vertex, indexed by path
+        // depth and `s`.
+        let mut strategy_films: Vec<Vec<Film>> = Vec::new();
+        if self.visualize_strategies {
+            for depth in 0..=self.max_depth {
+                strategy_films.push(
+                    (0..=depth + 1)
+                        .map(|s| camera.film().sibling(&format!("s{s}_t{}", depth + 2 - s)))
+                        .collect(),
+                );
+            }
+        }
+
+        let mut splats = Vec::new();
+        let mut strategies = Vec::new();
+        for y in 0..resolution.1 {
+            for x in 0..resolution.0 {
+                for index in 0..renderer.spp {
+                    sampler.start_pixel_sample((x, y), index);
+                    let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) else {
+                        continue;
+                    };
+                    splats.clear();
+                    strategies.clear();
+                    let l = self.sample(
+                        &ray,
+                        scene,
+                        Some(camera),
+                        sampler,
+                        &mut splats,
+                        self.visualize_strategies.then_some(&mut strategies),
+                    );
+                    let film = camera.film_mut();
+                    film.add_sample((x, y), &l);
+                    for (raster, contribution) in &splats {
+                        film.add_splat(*raster, contribution);
+                    }
+                    for strategy in &strategies {
+                        let raster = strategy.raster.unwrap_or((x as f64 + 0.5, y as f64 + 0.5));
+                        strategy_films[strategy.s + strategy.t - 2][strategy.s]
+                            .add_splat(raster, &strategy.contribution);
+                    }
+                }
+            }
+        }
+
+        let splat_scale = 1.0 / renderer.spp.max(1) as f64;
+        camera.film_mut().set_splat_scale(splat_scale);
+        for film in strategy_films.iter_mut().flatten() {
+            film.set_splat_scale(splat_scale);
+            if let Err(err) = film.save() {
+                eprintln!("Failed to write {}: {err}", film.filename);
+            }
+        }
+    }
+}
+
+/// Extend `path` by tracing `ray` through the scene and sampling the BSDF at
+/// every hit, adding at most `max_depth` vertices. `pdf` is the solid angle
+/// density of the direction of `ray`.
+#[allow(clippy::too_many_arguments)]
+fn random_walk<'a>(
+    scene: &'a Scene,
+    mut ray: Ray,
+    sampler: &mut dyn Sampler,
+    mut beta: Colour,
+    pdf: f64,
+    max_depth: usize,
+    mode: TransportMode,
+    path: &mut Vec<Vertex<'a>>,
+) {
+    if max_depth == 0 {
+        return;
+    }
+    let mut pdf_fwd = pdf;
+    let mut bounces = 0;
+    while let Some(hit) = scene.find_first_hit(&ray) {
+        let wo = -ray.d.normalize();
+        let prev = path.len() - 1;
+        let mut vertex = Vertex::surface(hit, wo, beta);
+        vertex.pdf_fwd = path[prev].convert_density(pdf_fwd, &vertex);
+
+        bounces += 1;
+        if bounces >= max_depth {
+            path.push(vertex);
+            break;
+        }
+
+        let interaction = hit
+            .primitive
+            .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+        let Some(wi) = interaction.scattered_direction else {
+            path.push(vertex);
+            break;
+        };
+        if interaction.attenuation.is_black() {
+            path.push(vertex);
+            break;
+        }
+        beta *= interaction.attenuation;
+        if mode == TransportMode::Importance {
+            beta *= shading_normal_correction(&hit, &wo, &wi);
+        }
+        let pdf_rev = if interaction.specular {
+            vertex.delta = true;
+            pdf_fwd = 0.0;
+            0.0
+        } else {
+            pdf_fwd = interaction.pdf;
+            hit.material.pdf(&hit, &wi, &wo)
+        };
+        path[prev].pdf_rev = vertex.convert_density(pdf_rev, &path[prev]);
+        ray = hit.spawn_ray(&wi);
+        path.push(vertex);
+    }
+}
+
+/// Whether the segment between `a` and `b` is free of occluders.
+fn visible(scene: &Scene, a: &Vertex, b: &Vertex) -> bool {
+    let d = b.p - a.p;
+    let origin = offset_origin(&a.p, &a.n, &d);
+    let target = offset_origin(&b.p, &b.n, &-d);
+    scene.unoccluded(&Ray::new(origin, target - origin))
+}
+
+/// Geometry term between two vertices, including visibility.
+fn g(scene: &Scene, a: &Vertex, b: &Vertex) -> f64 {
+    let d = a.p - b.p;
+    let dist_squared = d.length_squared();
+    if dist_squared == 0.0 || !visible(scene, a, b) {
+        return 0.0;
+    }
+    let d = d / dist_squared.sqrt();
+    let mut g = 1.0 / dist_squared;
+    if a.is_on_surface() {
+        g *= a.ns.dot(&d).abs();
+    }
+    if b.is_on_surface() {
+        g *= b.ns.dot(&d).abs();
+    }
+    g
+}
+
+/// Weighted contribution of the path made of the first `s` light vertices
+/// and the first `t` camera vertices, and the raster position it lands on
+/// when it is splatted.
+fn connect(
+    scene: &Scene,
+    camera: Option<&Camera>,
+    light_vertices: &[Vertex],
+    camera_vertices: &[Vertex],
+    s: usize,
+    t: usize,
+    sampler: &mut dyn Sampler,
+) -> (Colour, Option<(f64, f64)>) {
+    let mut l = Colour::new();
+    let mut raster = None;
+    let mut sampled = None;
+
+    if s == 0 {
+        // The camera subpath hit a light by itself.
+        let pt = &camera_vertices[t - 1];
+        if pt.as_light().is_some() {
+            l = pt.le(&camera_vertices[t - 2]) * pt.beta;
+        }
+    } else if t == 1 {
+        // Connect the light subpath to the eye.
+        let qs = &light_vertices[s - 1];
+        if let Some(camera) = camera
+            && qs.is_connectible()
+            && let Some(sample) = camera.sample_wi(&qs.p)
+            && sample.pdf > 0.0
+            && !sample.we.is_black()
+        {
+            let vertex = Vertex::camera(sample.p, sample.we / sample.pdf);
+            l = qs.beta * qs.f(&vertex, TransportMode::Importance) * vertex.beta;
+            if qs.is_on_surface() {
+                l *= sample.wi.dot(&qs.ns).abs();
+            }
+            if !l.is_black() && !visible(scene, qs, &vertex) {
+                l = Colour::new();
+            }
+            raster = Some(sample.raster);
+            sampled = Some(vertex);
+        }
+    } else if s == 1 {
+        // Sample a fresh point on a light for the camera subpath.
+        let pt = &camera_vertices[t - 1];
+        let n_lights = scene.lights().len();
+        if pt.is_connectible()
+            && let Some((light, light_pdf)) = pick_light(scene, sampler.get_1d())
+            && let Some(sample) = light.sample_li(&pt.p, sampler.get_2d())
+            && sample.pdf > 0.0
+            && !sample.radiance.is_black()
+        {
+            let mut vertex = Vertex::light(
+                light,
+                sample.p,
+                sample.n,
+                sample.radiance / (sample.pdf * light_pdf),
+                0.0,
+            );
+            vertex.pdf_fwd = vertex.pdf_light_origin(n_lights, pt);
+            l = pt.beta * pt.f(&vertex, TransportMode::Radiance) * vertex.beta;
+            if pt.is_on_surface() {
+                l *= sample.wi.dot(&pt.ns).abs();
+            }
+            if !l.is_black() && !visible(scene, pt, &vertex) {
+                l = Colour::new();
+            }
+            sampled = Some(vertex);
+        }
+    } else {
+        // Join the two subpaths with a deterministic segment.
+        let qs = &light_vertices[s - 1];
+        let pt = &camera_vertices[t - 1];
+        if qs.is_connectible() && pt.is_connectible() {
+            l = qs.beta
+                * qs.f(pt, TransportMode::Importance)
+                * pt.f(qs, TransportMode::Radiance)
+                * pt.beta;
+            if !l.is_black() {
+                l *= g(scene, qs, pt);
+            }
+        }
+    }
+
+    if l.is_black() {
+        return (l, raster);
+    }
+    let weight = mis_weight(
+        scene,
+        camera,
+        light_vertices,
+        camera_vertices,
+        sampled,
+        s,
+        t,
+    );
+    (l * weight, raster)
+}
+
+/// Balance heuristic weight of the `(s, t)` strategy, computed from the
+/// ratios of the densities every other strategy would have sampled the same
+/// path with.
+fn mis_weight<'a>(
+    scene: &Scene,
+    camera: Option<&Camera>,
+    light_vertices: &[Vertex<'a>],
+    camera_vertices: &[Vertex<'a>],
+    sampled: Option<Vertex<'a>>,
+    s: usize,
+    t: usize,
+) -> f64 {
+    if s + t == 2 {
+        return 1.0;
+    }
+    let mut lv = light_vertices[..s].to_vec();
+    let mut cv = camera_vertices[..t].to_vec();
+    if let Some(sampled) = sampled {
+        if s == 1 {
+            lv[0] = sampled;
+        } else if t == 1 {
+            cv[0] = sampled;
+        }
+    }
+
+    // The connection vertices are never degenerate.
+    cv[t - 1].delta = false;
+    if s > 0 {
+        lv[s - 1].delta = false;
+    }
+
+    // Densities of the reverse direction at the connection.
+    let n_lights = scene.lights().len();
+    cv[t - 1].pdf_rev = if s > 0 {
+        lv[s - 1].pdf(camera, s.checked_sub(2).map(|i| &lv[i]), &cv[t - 1])
+    } else {
+        cv[t - 1].pdf_light_origin(n_lights, &cv[t - 2])
+    };
+    if t > 1 {
+        cv[t - 2].pdf_rev = if s > 0 {
+            cv[t - 1].pdf(camera, Some(&lv[s - 1]), &cv[t - 2])
+        } else {
+            cv[t - 1].pdf_light(&cv[t - 2])
+        };
+    }
+    if s > 0 {
+        lv[s - 1].pdf_rev = cv[t - 1].pdf(camera, t.checked_sub(2).map(|i| &cv[i]), &lv[s - 1]);
+    }
+    if s > 1 {
+        lv[s - 2].pdf_rev = lv[s - 1].pdf(camera, Some(&cv[t - 1]), &lv[s - 2]);
+    }
+
+    let remap = |pdf: f64| if pdf != 0.0 { pdf } else { 1.0 };
+    let mut sum_ri = 0.0;
+
+    // Strategies with fewer camera vertices. Without a camera the light
+    // subpath cannot be connected to the eye, so stop at two vertices.
+    let last = if camera.is_some() { 1 } else { 2 };
+    let mut ri = 1.0;
+    for i in (last..t).rev() {
+        ri *= remap(cv[i].pdf_rev) / remap(cv[i].pdf_fwd);
+        if !cv[i].delta && !cv[i - 1].delta {
+            sum_ri += ri;
+        }
+    }
+
+    // Strategies with fewer light vertices.
+    let mut ri = 1.0;
+    for i in (0..s).rev() {
+        ri *= remap(lv[i].pdf_rev) / remap(lv[i].pdf_fwd);
+        let delta_light_vertex = if i > 0 {
+            lv[i - 1].delta
+        } else {
+            lv[0].is_delta_light()
+        };
+        if !lv[i].delta && !delta_light_vertex {
+            sum_ri += ri;
+        }
+    }
+    1.0 / (1.0 + sum_ri)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        integrator::path::PathIntegrator,
+        material::Material,
+        math::{matrix4::Matrix4, transform::Transform},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    /// Average of the pixels of the image `integrator` renders of a diffuse
+    /// sphere on a floor under a spherical light.
+    fn render_mean(integrator: &dyn Integrator, spp: usize) -> f64 {
+        let mut scene = Scene::new();
+        let diffuse = Arc::new(Material::diffuse(Colour::grey(0.6)));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, -1000.0])),
+            diffuse.clone(),
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, 1.0])),
+            diffuse,
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.5)),
+            Transform::from_matrix(Matrix4::new_translate([1.5, -1.5, 3.0])),
+            Arc::new(Material::light(Colour::grey(10.0))),
+        ));
+
+        let mut camera = Camera::new(
+            Vec3::new(0.0, -6.0, 2.0),
+            Vec3::new(0.0, 6.0, -1.5),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.5,
+            (4, 4),
+            String::new(),
+        );
+        let mut sampler = IndependentSampler::new(11);
+        Renderer::new(spp).render(integrator, &scene, &mut camera, &mut sampler);
+        let film = camera.film();
+        let (width, height) = film.resolution();
+        let sum: f64 = (0..height)
+            .flat_map(|y| (0..width).map(move |x| film.get_pixel((x, y)).luminance()))
+            .sum();
+        sum / (width * height) as f64
+    }
+
+    #[test]
+    fn test_matches_path_tracing_on_diffuse_scene() {
+        let bdpt = render_mean(&BdptIntegrator::new(3, false), 1024);
+        let path = render_mean(&PathIntegrator::new(3, 10), 4096);
+        assert!((bdpt - path).abs() < 0.05 * path, "{bdpt} {path}");
+    }
+}
@@ -0,0 +1,77 @@
+use clap::ValueEnum;
+
+use crate::{
+    common::{rng::mix_bits, sampler::Sampler},
+    integrator::Integrator,
+    math::vec3::Vec3,
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+};
+
+/// Attribute of the first hit shown by the debug integrator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DebugMode {
+    /// World space shading normal, mapped from `[-1, 1]` to `[0, 1]`.
+    Normal,
+    /// World space geometric normal, mapped like `Normal`.
+    GeometricNormal,
+    /// Hit distance, bright close to the eye and fading with distance.
+    T,
+    /// Texture coordinates in the red and green channels.
+    Uv,
+    /// Fractional part of the world space position, which draws a unit grid
+    /// over the scene.
+    Position,
+    /// A random colour per primitive.
+    Primitive,
+}
+
+/// Render a field of the `HitRecord` of each first hit as false colour, to
+/// check intersections and transforms independently of shading.
+pub struct DebugIntegrator {
+    mode: DebugMode,
+}
+
+impl DebugIntegrator {
+    pub fn new(mode: DebugMode) -> Self {
+        Self { mode }
+    }
+}
+
+impl Integrator for DebugIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Colour {
+        let Some(hit) = scene.find_first_hit(ray) else {
+            return Colour::new();
+        };
+        match self.mode {
+            DebugMode::Normal => normal_colour(&hit.ns),
+            DebugMode::GeometricNormal => normal_colour(&hit.n),
+            DebugMode::T => {
+                // `t` is measured along the ray direction, make it a distance.
+                let distance = hit.t * ray.d.length();
+                Colour::grey(1.0 / (1.0 + distance))
+            }
+            DebugMode::Uv => Colour::rgb(hit.uv.0.fract(), hit.uv.1.fract(), 0.0),
+            DebugMode::Position => Colour::rgb(
+                hit.p.x - hit.p.x.floor(),
+                hit.p.y - hit.p.y.floor(),
+                hit.p.z - hit.p.z.floor(),
+            ),
+            DebugMode::Primitive => {
+                let index = scene
+                    .primitives()
+                    .iter()
+                    .position(|primitive| std::ptr::eq(primitive, hit.primitive))
+                    .unwrap_or(usize::MAX);
+                let hash = mix_bits(index as u64);
+                let channel = |shift: u32| ((hash >> shift) & 0xff) as f64 / 255.0;
+                Colour::rgb(channel(0), channel(8), channel(16))
+            }
+        }
+    }
+}
+
+/// Map a unit vector from `[-1, 1]` to `[0, 1]` per channel.
+fn normal_colour(n: &Vec3) -> Colour {
+    Colour::rgb(n.x + 1.0, n.y + 1.0, n.z + 1.0) * 0.5
+}
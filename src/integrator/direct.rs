@@ -0,0 +1,120 @@
+use clap::ValueEnum;
+
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, uniform_sample_all_lights, uniform_sample_one_light},
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+};
+
+/// How the direct lighting integrator picks the lights it samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LightStrategy {
+    /// Take one sample from every light at each hit.
+    All,
+    /// Take one sample from a light picked uniformly at random.
+    One,
+}
+
+/// Direct illumination only, with light and BSDF sampling combined by
+/// multiple importance sampling. Perfect reflection and refraction are
+/// followed up to `max_depth` bounces.
+pub struct DirectLightingIntegrator {
+    strategy: LightStrategy,
+    max_depth: usize,
+}
+
+impl DirectLightingIntegrator {
+    pub fn new(strategy: LightStrategy, max_depth: usize) -> Self {
+        Self {
+            strategy,
+            max_depth,
+        }
+    }
+
+    fn trace(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, depth: usize) -> Colour {
+        let Some(hit) = scene.find_first_hit(ray) else {
+            return Colour::new();
+        };
+        let material = hit.material;
+        let wo = -ray.d.normalize();
+
+        let mut l = material.emitted(&hit, &wo);
+        l += match self.strategy {
+            LightStrategy::All => uniform_sample_all_lights(&hit, &wo, scene, sampler),
+            LightStrategy::One => uniform_sample_one_light(&hit, &wo, scene, sampler),
+        };
+
+        if depth < self.max_depth {
+            let lobes = material.specular_lobes(&hit, &wo);
+            for (wi, weight) in lobes.reflection.into_iter().chain(lobes.transmission) {
+                if !weight.is_black() {
+                    l += weight * self.trace(&hit.spawn_ray(&wi), scene, sampler, depth + 1);
+                }
+            }
+        }
+        l
+    }
+}
+
+impl Integrator for DirectLightingIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.trace(ray, scene, sampler, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        material::Material,
+        math::{matrix4::Matrix4, transform::Transform, vec3::Vec3},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    #[test]
+    fn test_one_light_matches_uniform_sample_all_lights() {
+        // A diffuse floor lit by a single spherical light.
+        let mut scene = Scene::new();
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, -1000.0])),
+            Arc::new(Material::diffuse(Colour::grey(0.5))),
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.5)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, 3.0])),
+            Arc::new(Material::light(Colour::grey(4.0))),
+        ));
+
+        let ray = Ray::new(Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = scene.find_first_hit(&ray).unwrap();
+        let wo = -ray.d;
+        let mut sampler = IndependentSampler::new(3);
+        let n = 20000;
+        let mut expected = 0.0;
+        for index in 0..n {
+            sampler.start_pixel_sample((0, 0), index);
+            expected += uniform_sample_all_lights(&hit, &wo, &scene, &mut sampler).luminance();
+        }
+        let expected = expected / n as f64;
+        assert!(expected > 0.0);
+
+        for strategy in [LightStrategy::All, LightStrategy::One] {
+            let integrator = DirectLightingIntegrator::new(strategy, 0);
+            let mut l = 0.0;
+            for index in 0..n {
+                sampler.start_pixel_sample((1, 0), index);
+                l += integrator.li(&ray, &scene, &mut sampler).luminance();
+            }
+            let l = l / n as f64;
+            assert!(
+                (l - expected).abs() < 0.02 * expected,
+                "{strategy:?}: {l} {expected}"
+            );
+        }
+    }
+}
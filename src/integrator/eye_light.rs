@@ -0,0 +1,24 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::Integrator,
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+};
+
+/// Shade each first hit as if lit by a light at the eye. Cheap and noise
+/// free, useful to check the camera and the geometry.
+#[derive(Default)]
+pub struct EyeLightIntegrator;
+
+impl Integrator for EyeLightIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        let Some(hit) = scene.find_first_hit(ray) else {
+            return Colour::new();
+        };
+        let wo = -ray.d.normalize();
+        let interaction = hit
+            .primitive
+            .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+        interaction.colour + interaction.attenuation * hit.n.dot(&wo).abs()
+    }
+}
@@ -0,0 +1,338 @@
+use clap::ValueEnum;
+
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, path::PathIntegrator},
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+};
+
+/// Number of conjugate gradient iterations of a screened Poisson solve.
+const CG_ITERATIONS: usize = 200;
+/// Number of reweighting passes of the L1 reconstruction.
+const L1_ITERATIONS: usize = 10;
+/// Residuals below this get the same weight in the L1 reconstruction.
+const L1_EPSILON: f64 = 1e-3;
+
+/// Norm the reconstructed image minimizes the error to the primal image
+/// and to the gradients in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Reconstruction {
+    /// Robust to outliers, the usual choice.
+    L1,
+    /// Unbiased, but spreads outliers into smooth blotches.
+    L2,
+}
+
+/// Gradient-domain path tracing (Kettunen et al. 2015). Every base path is
+/// shifted to the four neighbouring pixels by replaying its random numbers
+/// through the neighbour, which gives strongly correlated paths whose
+/// difference estimates the image gradients with little noise. The final
+/// image solves a screened Poisson problem combining the noisy primal image
+/// with the gradients.
+///
+/// The primal image and the absolute gradients are saved next to the final
+/// image.
+pub struct GradientPathIntegrator {
+    path: PathIntegrator,
+    reconstruction: Reconstruction,
+    /// Weight of the primal image relative to the gradients.
+    alpha: f64,
+}
+
+impl GradientPathIntegrator {
+    pub fn new(path: PathIntegrator, reconstruction: Reconstruction, alpha: f64) -> Self {
+        Self {
+            path,
+            reconstruction,
+            alpha,
+        }
+    }
+
+    /// Trace the `index`-th path through `pixel` with the random numbers of
+    /// the `index`-th sample of `base`.
+    fn replay(
+        &self,
+        scene: &Scene,
+        camera: &Camera,
+        sampler: &mut dyn Sampler,
+        base: (usize, usize),
+        pixel: (usize, usize),
+        index: usize,
+    ) -> Colour {
+        sampler.start_pixel_sample(base, index);
+        let Some(ray) = camera.get_camera_sample(pixel, sampler.get_2d()) else {
+            return Colour::new();
+        };
+        self.path.li(&ray, scene, sampler)
+    }
+}
+
+impl Integrator for GradientPathIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.path.li(ray, scene, sampler)
+    }
+
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let (width, height) = camera.film().resolution();
+        if width == 0 || height == 0 || renderer.spp == 0 {
+            return;
+        }
+        // Gradient `dx[i]` is between pixel `i` and the one to its right,
+        // `dy[i]` between pixel `i` and the one below.
+        let mut dx = vec![Colour::new(); width * height];
+        let mut dy = vec![Colour::new(); width * height];
+        // Each gradient is estimated from both of its pixels.
+        let weight = 0.5 / renderer.spp as f64;
+
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                for index in 0..renderer.spp {
+                    let base = self.replay(scene, camera, sampler, (x, y), (x, y), index);
+                    camera.film_mut().add_sample((x, y), &base);
+
+                    if x + 1 < width {
+                        let offset = self.replay(scene, camera, sampler, (x, y), (x + 1, y), index);
+                        dx[i] += (offset - base) * weight;
+                    }
+                    if x > 0 {
+                        let offset = self.replay(scene, camera, sampler, (x, y), (x - 1, y), index);
+                        dx[i - 1] += (base - offset) * weight;
+                    }
+                    if y + 1 < height {
+                        let offset = self.replay(scene, camera, sampler, (x, y), (x, y + 1), index);
+                        dy[i] += (offset - base) * weight;
+                    }
+                    if y > 0 {
+                        let offset = self.replay(scene, camera, sampler, (x, y), (x, y - 1), index);
+                        dy[i - width] += (base - offset) * weight;
+                    }
+                }
+            }
+        }
+
+        let primal = camera.film().pixels();
+        for (suffix, image) in [("primal", &primal), ("dx", &dx), ("dy", &dy)] {
+            let mut film = camera.film().sibling(suffix);
+            for (i, colour) in image.iter().enumerate() {
+                let abs = Colour::rgb(colour.r.abs(), colour.g.abs(), colour.b.abs());
+                film.set_pixel((i % width, i / width), &abs);
+            }
+            if let Err(err) = film.save() {
+                eprintln!("Failed to write {}: {err}", film.filename);
+            }
+        }
+
+        let channels: Vec<Vec<f64>> = (0..3)
+            .map(|c| {
+                let image = Image {
+                    width,
+                    height,
+                    primal: primal.iter().map(|p| p[c]).collect(),
+                    dx: dx.iter().map(|g| g[c]).collect(),
+                    dy: dy.iter().map(|g| g[c]).collect(),
+                };
+                match self.reconstruction {
+                    Reconstruction::L1 => image.solve_l1(self.alpha),
+                    Reconstruction::L2 => {
+                        image.solve(self.alpha, &Weights::uniform(width * height), None)
+                    }
+                }
+            })
+            .collect();
+        let film = camera.film_mut();
+        for (i, ((r, g), b)) in channels[0]
+            .iter()
+            .zip(&channels[1])
+            .zip(&channels[2])
+            .enumerate()
+        {
+            film.set_pixel((i % width, i / width), &Colour::rgb(*r, *g, *b));
+        }
+    }
+}
+
+/// One channel of a primal image and of its gradients.
+struct Image {
+    width: usize,
+    height: usize,
+    primal: Vec<f64>,
+    dx: Vec<f64>,
+    dy: Vec<f64>,
+}
+
+/// Weights of the terms of the screened Poisson energy.
+struct Weights {
+    primal: Vec<f64>,
+    dx: Vec<f64>,
+    dy: Vec<f64>,
+}
+
+impl Weights {
+    fn uniform(n: usize) -> Self {
+        Self {
+            primal: vec![1.0; n],
+            dx: vec![1.0; n],
+            dy: vec![1.0; n],
+        }
+    }
+}
+
+impl Image {
+    /// The finite differences of `x` to the right and downwards.
+    fn gradients(&self, x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let mut dx = vec![0.0; x.len()];
+        let mut dy = vec![0.0; x.len()];
+        for y in 0..self.height {
+            for i in y * self.width..(y + 1) * self.width {
+                if i % self.width + 1 < self.width {
+                    dx[i] = x[i + 1] - x[i];
+                }
+                if y + 1 < self.height {
+                    dy[i] = x[i + self.width] - x[i];
+                }
+            }
+        }
+        (dx, dy)
+    }
+
+    /// Add the transposed finite differences of `dx` and `dy` to `out`.
+    fn add_transposed(&self, dx: &[f64], dy: &[f64], out: &mut [f64]) {
+        for y in 0..self.height {
+            for i in y * self.width..(y + 1) * self.width {
+                if i % self.width + 1 < self.width {
+                    out[i + 1] += dx[i];
+                    out[i] -= dx[i];
+                }
+                if y + 1 < self.height {
+                    out[i + self.width] += dy[i];
+                    out[i] -= dy[i];
+                }
+            }
+        }
+    }
+
+    /// The product of the normal equations matrix with `x`.
+    fn apply(&self, alpha2: f64, weights: &Weights, x: &[f64]) -> Vec<f64> {
+        let (mut dx, mut dy) = self.gradients(x);
+        dx.iter_mut().zip(&weights.dx).for_each(|(d, w)| *d *= w);
+        dy.iter_mut().zip(&weights.dy).for_each(|(d, w)| *d *= w);
+        let mut out: Vec<f64> = x
+            .iter()
+            .zip(&weights.primal)
+            .map(|(x, w)| alpha2 * w * x)
+            .collect();
+        self.add_transposed(&dx, &dy, &mut out);
+        out
+    }
+
+    /// Minimize `alpha^2 sum w (x - primal)^2 + sum w (grad x - gradients)^2`
+    /// with conjugate gradients, starting from `initial` or the primal
+    /// image. The weights multiply the squared residuals, they are not
+    /// squared themselves.
+    fn solve(&self, alpha: f64, weights: &Weights, initial: Option<Vec<f64>>) -> Vec<f64> {
+        let alpha2 = alpha * alpha;
+        let mut b: Vec<f64> = self
+            .primal
+            .iter()
+            .zip(&weights.primal)
+            .map(|(p, w)| alpha2 * w * p)
+            .collect();
+        let wdx: Vec<f64> = self
+            .dx
+            .iter()
+            .zip(&weights.dx)
+            .map(|(g, w)| g * w)
+            .collect();
+        let wdy: Vec<f64> = self
+            .dy
+            .iter()
+            .zip(&weights.dy)
+            .map(|(g, w)| g * w)
+            .collect();
+        self.add_transposed(&wdx, &wdy, &mut b);
+
+        let mut x = initial.unwrap_or_else(|| self.primal.clone());
+        let ax = self.apply(alpha2, weights, &x);
+        let mut r: Vec<f64> = b.iter().zip(&ax).map(|(b, ax)| b - ax).collect();
+        let mut p = r.clone();
+        let mut rr: f64 = r.iter().map(|r| r * r).sum();
+        let tolerance = 1e-12 * b.iter().map(|b| b * b).sum::<f64>().max(f64::MIN_POSITIVE);
+        for _ in 0..CG_ITERATIONS {
+            if rr <= tolerance {
+                break;
+            }
+            let ap = self.apply(alpha2, weights, &p);
+            let pap: f64 = p.iter().zip(&ap).map(|(p, ap)| p * ap).sum();
+            if pap <= 0.0 {
+                break;
+            }
+            let step = rr / pap;
+            x.iter_mut().zip(&p).for_each(|(x, p)| *x += step * p);
+            r.iter_mut().zip(&ap).for_each(|(r, ap)| *r -= step * ap);
+            let rr_next: f64 = r.iter().map(|r| r * r).sum();
+            let beta = rr_next / rr;
+            p.iter_mut().zip(&r).for_each(|(p, r)| *p = r + beta * *p);
+            rr = rr_next;
+        }
+        x
+    }
+
+    /// Minimize the L1 version of the energy by iteratively reweighted least
+    /// squares.
+    fn solve_l1(&self, alpha: f64) -> Vec<f64> {
+        let n = self.primal.len();
+        let mut x = self.solve(alpha, &Weights::uniform(n), None);
+        for _ in 0..L1_ITERATIONS {
+            let (dx, dy) = self.gradients(&x);
+            let reweight = |r: f64| 1.0 / r.abs().max(L1_EPSILON);
+            let weights = Weights {
+                primal: (0..n).map(|i| reweight(x[i] - self.primal[i])).collect(),
+                dx: (0..n).map(|i| reweight(dx[i] - self.dx[i])).collect(),
+                dy: (0..n).map(|i| reweight(dy[i] - self.dy[i])).collect(),
+            };
+            x = self.solve(alpha, &weights, Some(x));
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconstruction_recovers_image_from_exact_gradients() {
+        let (width, height) = (7, 5);
+        let truth: Vec<f64> = (0..width * height)
+            .map(|i| ((i % width) as f64 * 0.7).sin() + (i / width) as f64 * 0.3)
+            .collect();
+        let mut image = Image {
+            width,
+            height,
+            primal: truth.clone(),
+            dx: Vec::new(),
+            dy: Vec::new(),
+        };
+        (image.dx, image.dy) = image.gradients(&truth);
+        // A primal image that is off everywhere by the same amount.
+        image.primal.iter_mut().for_each(|p| *p += 1.0);
+
+        let l2 = image.solve(0.2, &Weights::uniform(width * height), None);
+        for (x, t) in l2.iter().zip(&truth) {
+            assert!((x - (t + 1.0)).abs() < 1e-6);
+        }
+        // An outlier in the primal image is ignored by the L1 solve.
+        image.primal = truth.clone();
+        image.primal[17] += 100.0;
+        let l1 = image.solve_l1(0.2);
+        assert!((l1[17] - truth[17]).abs() < 0.1, "{}", l1[17]);
+    }
+}
@@ -0,0 +1,408 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::{sampling::uniform_sample_sphere, vec3::Vec3},
+};
+
+/// Deepest level of a directional quadtree.
+const MAX_DTREE_DEPTH: usize = 20;
+/// Share of the energy above which a directional quad is subdivided.
+const DTREE_THRESHOLD: f64 = 0.01;
+/// Number of path vertices, scaled by `sqrt(2^iteration)`, above which a
+/// spatial leaf is split.
+const STREE_THRESHOLD: f64 = 12000.0;
+
+/// Map a direction to the unit square with the equal-area cylindrical
+/// projection `(cos(theta), phi)`.
+fn dir_to_canonical(d: &Vec3) -> (f64, f64) {
+    let cos_theta = d.z.clamp(-1.0, 1.0);
+    let mut phi = d.y.atan2(d.x);
+    if phi < 0.0 {
+        phi += 2.0 * PI;
+    }
+    (
+        ((cos_theta + 1.0) * 0.5).min(1.0),
+        (phi / (2.0 * PI)).min(1.0),
+    )
+}
+
+fn canonical_to_dir(p: (f64, f64)) -> Vec3 {
+    let cos_theta = 2.0 * p.0 - 1.0;
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * p.1;
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
+
+/// Node of a directional quadtree. Child `i` covers the quadrant
+/// `(i & 1, i >> 1)` of the node, and `sums[i]` is the energy recorded in it.
+#[derive(Clone, Copy, Default)]
+struct QuadNode {
+    sums: [f64; 4],
+    /// Index of the child nodes, 0 for leaves.
+    children: [usize; 4],
+}
+
+impl QuadNode {
+    fn total(&self) -> f64 {
+        self.sums.iter().sum()
+    }
+}
+
+/// Quadtree over the sphere of directions, storing how much incident
+/// radiance was recorded in each quad.
+#[derive(Clone)]
+struct DTree {
+    nodes: Vec<QuadNode>,
+}
+
+impl Default for DTree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![QuadNode::default()],
+        }
+    }
+}
+
+impl DTree {
+    fn total(&self) -> f64 {
+        self.nodes[0].total()
+    }
+
+    fn record(&mut self, d: &Vec3, value: f64) {
+        let mut p = dir_to_canonical(d);
+        let mut node = 0;
+        loop {
+            let (child, next) = quadrant(p);
+            p = next;
+            self.nodes[node].sums[child] += value;
+            node = self.nodes[node].children[child];
+            if node == 0 {
+                break;
+            }
+        }
+    }
+
+    fn pdf(&self, d: &Vec3) -> f64 {
+        let mut p = dir_to_canonical(d);
+        let mut pdf = 1.0 / (4.0 * PI);
+        let mut node = 0;
+        loop {
+            let total = self.nodes[node].total();
+            if total <= 0.0 {
+                break;
+            }
+            let (child, next) = quadrant(p);
+            p = next;
+            pdf *= 4.0 * self.nodes[node].sums[child] / total;
+            node = self.nodes[node].children[child];
+            if node == 0 {
+                break;
+            }
+        }
+        pdf
+    }
+
+    /// Pick a quad proportionally to its energy and a direction uniformly
+    /// inside it.
+    fn sample(&self, mut u: (f64, f64)) -> Vec3 {
+        if self.total() <= 0.0 {
+            return uniform_sample_sphere(u);
+        }
+        let mut origin = (0.0, 0.0);
+        let mut size = 1.0;
+        let mut node = 0;
+        loop {
+            let sums = self.nodes[node].sums;
+            let total = sums[0] + sums[1] + sums[2] + sums[3];
+            if total <= 0.0 {
+                break;
+            }
+            size *= 0.5;
+            let p_left = (sums[0] + sums[2]) / total;
+            let x = pick(&mut u.0, p_left);
+            let p_bottom = sums[x] / (sums[x] + sums[x + 2]);
+            let y = pick(&mut u.1, p_bottom);
+            origin.0 += x as f64 * size;
+            origin.1 += y as f64 * size;
+            node = self.nodes[node].children[x + 2 * y];
+            if node == 0 {
+                break;
+            }
+        }
+        canonical_to_dir((origin.0 + u.0 * size, origin.1 + u.1 * size))
+    }
+
+    /// An empty tree subdividing the quads holding more than
+    /// `DTREE_THRESHOLD` of the energy of this one, with at most
+    /// `max_nodes` nodes.
+    fn refined(&self, max_nodes: usize) -> DTree {
+        let total = self.total();
+        let mut tree = DTree::default();
+        if total <= 0.0 {
+            return tree;
+        }
+        // New node, node of this tree covering the same quad if any, energy
+        // of the quad and depth.
+        let mut stack = vec![(0, Some(0), total, 1)];
+        while let Some((node, source, energy, depth)) = stack.pop() {
+            for child in 0..4 {
+                let child_energy = match source {
+                    Some(source) => self.nodes[source].sums[child],
+                    None => energy / 4.0,
+                };
+                if depth >= MAX_DTREE_DEPTH
+                    || child_energy / total <= DTREE_THRESHOLD
+                    || tree.nodes.len() >= max_nodes
+                {
+                    continue;
+                }
+                let index = tree.nodes.len();
+                tree.nodes.push(QuadNode::default());
+                tree.nodes[node].children[child] = index;
+                let child_source = source
+                    .map(|source| self.nodes[source].children[child])
+                    .filter(|&c| c != 0);
+                stack.push((index, child_source, child_energy, depth + 1));
+            }
+        }
+        tree
+    }
+}
+
+/// Quadrant of `p` and the position of `p` inside it.
+fn quadrant(p: (f64, f64)) -> (usize, (f64, f64)) {
+    let x = usize::from(p.0 >= 0.5);
+    let y = usize::from(p.1 >= 0.5);
+    (
+        x + 2 * y,
+        (
+            (p.0 * 2.0 - x as f64).min(1.0),
+            (p.1 * 2.0 - y as f64).min(1.0),
+        ),
+    )
+}
+
+/// Pick 0 with probability `p` and 1 otherwise, remapping `u` to `[0, 1)`.
+fn pick(u: &mut f64, p: f64) -> usize {
+    if *u < p {
+        *u /= p;
+        0
+    } else {
+        *u = ((*u - p) / (1.0 - p)).min(1.0 - f64::EPSILON);
+        1
+    }
+}
+
+enum SpatialNode {
+    Inner {
+        axis: usize,
+        children: [usize; 2],
+    },
+    Leaf {
+        /// Distribution learned by the previous iteration.
+        sampling: DTree,
+        /// Distribution being recorded by the current iteration.
+        building: DTree,
+        samples: usize,
+    },
+}
+
+/// Spatial-directional tree of Müller et al. 2017, "Practical Path Guiding
+/// for Efficient Light-Transport Simulation". A binary tree splitting the
+/// scene bounds at the middle of alternating axes, with a quadtree of the
+/// incident radiance at every leaf.
+///
+/// Radiance recorded during an iteration is used for sampling once `refine`
+/// is called, which also grows both trees where most samples and energy
+/// were recorded.
+pub struct SdTree {
+    bounds: AABB,
+    nodes: Vec<SpatialNode>,
+}
+
+impl SdTree {
+    pub fn new(bounds: AABB) -> Self {
+        Self {
+            bounds,
+            nodes: vec![SpatialNode::Leaf {
+                sampling: DTree::default(),
+                building: DTree::default(),
+                samples: 0,
+            }],
+        }
+    }
+
+    fn leaf(&self, p: &Vec3) -> usize {
+        let mut p = self.bounds.offset(p);
+        let mut node = 0;
+        while let SpatialNode::Inner { axis, children } = &self.nodes[node] {
+            let child = usize::from(p[*axis] >= 0.5);
+            p[*axis] = p[*axis] * 2.0 - child as f64;
+            node = children[child];
+        }
+        node
+    }
+
+    fn sampling_tree(&self, p: &Vec3) -> &DTree {
+        match &self.nodes[self.leaf(p)] {
+            SpatialNode::Leaf { sampling, .. } => sampling,
+            SpatialNode::Inner { .. } => unreachable!(),
+        }
+    }
+
+    /// Whether radiance has been learned around `p`.
+    pub fn can_sample(&self, p: &Vec3) -> bool {
+        self.sampling_tree(p).total() > 0.0
+    }
+
+    pub fn sample(&self, p: &Vec3, u: (f64, f64)) -> Vec3 {
+        self.sampling_tree(p).sample(u)
+    }
+
+    pub fn pdf(&self, p: &Vec3, d: &Vec3) -> f64 {
+        self.sampling_tree(p).pdf(d)
+    }
+
+    /// Record `value`, an estimate of the radiance arriving at `p` from `d`
+    /// divided by the density `d` was sampled with.
+    pub fn record(&mut self, p: &Vec3, d: &Vec3, value: f64) {
+        if !value.is_finite() || value < 0.0 {
+            return;
+        }
+        let leaf = self.leaf(p);
+        if let SpatialNode::Leaf {
+            building, samples, ..
+        } = &mut self.nodes[leaf]
+        {
+            building.record(d, value);
+            *samples += 1;
+        }
+    }
+
+    fn memory(&self) -> usize {
+        self.nodes
+            .iter()
+            .map(|node| {
+                std::mem::size_of::<SpatialNode>()
+                    + match node {
+                        SpatialNode::Leaf {
+                            sampling, building, ..
+                        } => {
+                            (sampling.nodes.len() + building.nodes.len())
+                                * std::mem::size_of::<QuadNode>()
+                        }
+                        SpatialNode::Inner { .. } => 0,
+                    }
+            })
+            .sum()
+    }
+
+    /// End the training iteration `iteration`: split the spatial leaves that
+    /// received many samples, start sampling from the recorded radiance and
+    /// prepare refined quadtrees for the next iteration, keeping the trees
+    /// within `memory_budget` bytes.
+    pub fn refine(&mut self, iteration: usize, memory_budget: usize) {
+        let threshold = STREE_THRESHOLD * 2f64.powi(iteration as i32).sqrt();
+        let mut memory = self.memory();
+        let mut stack = vec![(0, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            match &self.nodes[node] {
+                SpatialNode::Inner { children, .. } => {
+                    stack.push((children[0], depth + 1));
+                    stack.push((children[1], depth + 1));
+                }
+                SpatialNode::Leaf {
+                    building, samples, ..
+                } => {
+                    let cost = 2 * std::mem::size_of::<SpatialNode>()
+                        + 2 * building.nodes.len() * std::mem::size_of::<QuadNode>();
+                    if (*samples as f64) < threshold || memory + cost > memory_budget {
+                        continue;
+                    }
+                    memory += cost;
+                    let (building, samples) = (building.clone(), *samples / 2);
+                    let first = self.nodes.len();
+                    for _ in 0..2 {
+                        self.nodes.push(SpatialNode::Leaf {
+                            sampling: DTree::default(),
+                            building: building.clone(),
+                            samples,
+                        });
+                    }
+                    self.nodes[node] = SpatialNode::Inner {
+                        axis: depth % 3,
+                        children: [first, first + 1],
+                    };
+                    stack.push((first, depth + 1));
+                    stack.push((first + 1, depth + 1));
+                }
+            }
+        }
+
+        let leaves = self
+            .nodes
+            .iter()
+            .filter(|node| matches!(node, SpatialNode::Leaf { .. }))
+            .count();
+        let spatial = self.nodes.len() * std::mem::size_of::<SpatialNode>();
+        let per_tree =
+            memory_budget.saturating_sub(spatial) / (2 * leaves * std::mem::size_of::<QuadNode>());
+        for node in &mut self.nodes {
+            if let SpatialNode::Leaf {
+                sampling,
+                building,
+                samples,
+            } = node
+            {
+                *sampling = std::mem::take(building);
+                *building = sampling.refined(per_tree.max(1));
+                *samples = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dtree_learns_normalized_density() {
+        // Radiance mostly arriving from above.
+        let record = |tree: &mut DTree| {
+            for i in 0..64 {
+                for j in 0..64 {
+                    let d =
+                        uniform_sample_sphere(((i as f64 + 0.5) / 64.0, (j as f64 + 0.5) / 64.0));
+                    tree.record(&d, 1.0 + 8.0 * d.z.max(0.0));
+                }
+            }
+        };
+        let mut tree = DTree::default();
+        for _ in 0..4 {
+            record(&mut tree);
+            tree = tree.refined(usize::MAX);
+        }
+        record(&mut tree);
+        assert!(tree.nodes.len() > 1);
+
+        // The density integrates to one over the sphere.
+        let n = 256;
+        let mut integral = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let d =
+                    canonical_to_dir(((i as f64 + 0.5) / n as f64, (j as f64 + 0.5) / n as f64));
+                integral += tree.pdf(&d) * 4.0 * PI / (n * n) as f64;
+            }
+        }
+        assert!((integral - 1.0).abs() < 1e-6, "{integral}");
+
+        // Sampled directions land where the energy was recorded.
+        let up = Vec3::new(0.0, 0.0, 1.0);
+        assert!(tree.pdf(&up) > tree.pdf(&-up));
+        let d = tree.sample((0.9, 0.3));
+        assert!(d.z > 0.0);
+    }
+}
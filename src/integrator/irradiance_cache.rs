@@ -0,0 +1,501 @@
+use std::{
+    cell::RefCell,
+    f64::consts::PI,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    accel::{aabb::AABB, octree::Octree},
+    common::sampler::Sampler,
+    integrator::{Integrator, path::PathIntegrator, uniform_sample_all_lights},
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::HitRecord,
+};
+
+/// Irradiance computed at a point, with its gradients and the distance to
+/// the surrounding geometry.
+#[derive(Clone, Copy, Debug)]
+struct IrradianceRecord {
+    p: Vec3,
+    n: Vec3,
+    e: Colour,
+    /// Harmonic mean distance to the surfaces seen from `p`.
+    r: f64,
+    /// Rotational gradient of each colour channel.
+    rotational: [Vec3; 3],
+    /// Translational gradient of each colour channel.
+    translational: [Vec3; 3],
+}
+
+impl IrradianceRecord {
+    /// Ward's error estimate, the weight of the record for a point at `p`
+    /// with normal `n`.
+    fn weight(&self, p: &Vec3, n: &Vec3) -> f64 {
+        let error = (*p - self.p).length() / self.r + (1.0 - n.dot(&self.n)).max(0.0).sqrt();
+        if error > 0.0 {
+            1.0 / error
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Irradiance extrapolated to `p` with normal `n` along the gradients.
+    fn extrapolate(&self, p: &Vec3, n: &Vec3) -> Colour {
+        let rotation = self.n.cross(n);
+        let translation = *p - self.p;
+        let channel = |i: usize| {
+            self.e[i] + rotation.dot(&self.rotational[i]) + translation.dot(&self.translational[i])
+        };
+        Colour::rgb(
+            channel(0).max(0.0),
+            channel(1).max(0.0),
+            channel(2).max(0.0),
+        )
+    }
+
+    fn to_line(self) -> String {
+        let mut values = vec![
+            self.p.x, self.p.y, self.p.z, self.n.x, self.n.y, self.n.z, self.e.r, self.e.g,
+            self.e.b, self.r,
+        ];
+        for g in self.rotational.iter().chain(&self.translational) {
+            values.extend([g.x, g.y, g.z]);
+        }
+        values
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let v = line
+            .split_whitespace()
+            .map(|v| v.parse::<f64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if v.len() != 28 {
+            return None;
+        }
+        let vec3 = |i: usize| Vec3::new(v[i], v[i + 1], v[i + 2]);
+        Some(Self {
+            p: vec3(0),
+            n: vec3(3),
+            e: Colour::rgb(v[6], v[7], v[8]),
+            r: v[9],
+            rotational: [vec3(10), vec3(13), vec3(16)],
+            translational: [vec3(19), vec3(22), vec3(25)],
+        })
+    }
+}
+
+/// Irradiance records indexed by an octree over the region they are valid
+/// in.
+pub struct IrradianceCache {
+    records: Vec<IrradianceRecord>,
+    octree: Octree<usize>,
+    /// Largest error of a record used for interpolation, the radius of the
+    /// validity region of a record in units of its harmonic mean distance.
+    accuracy: f64,
+}
+
+impl IrradianceCache {
+    pub fn new(bounds: AABB, accuracy: f64) -> Self {
+        Self {
+            records: Vec::new(),
+            octree: Octree::new(bounds),
+            accuracy,
+        }
+    }
+
+    fn add(&mut self, record: IrradianceRecord) {
+        let radius = self.accuracy * record.r;
+        let extent = Vec3::new(radius, radius, radius);
+        self.octree.add(
+            self.records.len(),
+            &AABB::new(record.p - extent, record.p + extent),
+        );
+        self.records.push(record);
+    }
+
+    /// Irradiance at `p` with normal `n` interpolated from the nearby
+    /// records, `None` if there are none.
+    fn interpolate(&self, p: &Vec3, n: &Vec3) -> Option<Colour> {
+        let mut sum = Colour::new();
+        let mut weights = 0.0;
+        self.octree.lookup(p, |&index| {
+            let record = &self.records[index];
+            let weight = record.weight(p, n);
+            if weight <= 1.0 / self.accuracy {
+                return;
+            }
+            // Records in front of `p` see occluders that `p` does not.
+            if (*p - record.p).dot(&(*n + record.n)) * 0.5 < -0.01 * record.r {
+                return;
+            }
+            let weight = weight.min(1e6);
+            sum += weight * record.extrapolate(p, n);
+            weights += weight;
+        });
+        (weights > 0.0).then(|| sum / weights)
+    }
+
+    /// Write the records to `path`, one per line.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        for record in &self.records {
+            writeln!(file, "{}", record.to_line())?;
+        }
+        file.flush()
+    }
+
+    /// Add the records saved in `path`.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let record = IrradianceRecord::from_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid record: {line}"),
+                )
+            })?;
+            self.add(record);
+        }
+        Ok(())
+    }
+}
+
+/// Irradiance caching (Ward et al. 1988) for diffuse interreflection.
+/// Indirect irradiance is computed lazily by sampling the hemisphere at
+/// sparse points, and interpolated elsewhere using its rotational and
+/// translational gradients (Ward and Heckbert 1992). Direct lighting is
+/// computed at every pixel and specular surfaces are followed, while glossy
+/// interreflection is ignored.
+///
+/// The cache can be saved to a file and loaded back, so that renders from
+/// another viewpoint only compute the records missing.
+pub struct IrradianceCacheIntegrator {
+    /// Maximum number of specular bounces of camera rays.
+    max_depth: usize,
+    /// Estimates the radiance arriving at the records.
+    path: PathIntegrator,
+    accuracy: f64,
+    /// Number of hemisphere rays traced for a record.
+    samples: usize,
+    /// Range the harmonic mean distance of the records is clamped to.
+    min_spacing: f64,
+    max_spacing: f64,
+    /// File the cache is loaded from, if it exists, and saved to.
+    file: Option<String>,
+    cache: RefCell<IrradianceCache>,
+}
+
+impl IrradianceCacheIntegrator {
+    pub fn new(
+        max_depth: usize,
+        rr_depth: usize,
+        accuracy: f64,
+        samples: usize,
+        min_spacing: f64,
+        max_spacing: f64,
+    ) -> Self {
+        Self {
+            max_depth,
+            path: PathIntegrator::new(max_depth.saturating_sub(1), rr_depth),
+            accuracy,
+            samples,
+            min_spacing,
+            max_spacing,
+            file: None,
+            cache: RefCell::new(IrradianceCache::new(AABB::empty(), accuracy)),
+        }
+    }
+
+    pub fn with_file(mut self, file: String) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Indirect irradiance at `hit` on the side of `n`, from the cache or
+    /// computed and added to it.
+    fn irradiance(
+        &self,
+        hit: &HitRecord,
+        n: &Vec3,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+    ) -> Colour {
+        if let Some(e) = self.cache.borrow().interpolate(&hit.p, n) {
+            return e;
+        }
+        let record = self.compute_record(hit, n, scene, sampler);
+        self.cache.borrow_mut().add(record);
+        record.e
+    }
+
+    /// Sample the hemisphere above `hit` with `m x n` strata in
+    /// `(sin^2(theta), phi)`, cosine distributed, and estimate the gradients
+    /// from the radiance and distances of neighbouring strata.
+    fn compute_record(
+        &self,
+        hit: &HitRecord,
+        normal: &Vec3,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+    ) -> IrradianceRecord {
+        let m = ((self.samples as f64 / PI).sqrt().round() as usize).max(1);
+        let n = (self.samples / m).max(1);
+        let (s, t) = normal.coordinate_system();
+        let tangent = |phi: f64| phi.cos() * s + phi.sin() * t;
+
+        let mut l = vec![Colour::new(); m * n];
+        let mut r = vec![f64::INFINITY; m * n];
+        let mut sin_theta = vec![0.0; m * n];
+        let mut e = Colour::new();
+        let mut inv_r_sum = 0.0;
+        for j in 0..m {
+            for k in 0..n {
+                let (u, v) = sampler.get_2d();
+                let sin2 = (j as f64 + u) / m as f64;
+                let phi = 2.0 * PI * (k as f64 + v) / n as f64;
+                let wi = normal.from_local(&Vec3::new(
+                    sin2.sqrt() * phi.cos(),
+                    sin2.sqrt() * phi.sin(),
+                    (1.0 - sin2).max(0.0).sqrt(),
+                ));
+                let ray = hit.spawn_ray(&wi);
+                let index = j * n + k;
+                sin_theta[index] = sin2.sqrt();
+                if let Some(first) = scene.find_first_hit(&ray) {
+                    r[index] = first.t * ray.d.length();
+                    inv_r_sum += 1.0 / r[index];
+                    // Emission is direct lighting, which is computed apart.
+                    l[index] =
+                        self.path.li(&ray, scene, sampler) - first.material.emitted(&first, &-wi);
+                }
+                e += l[index];
+            }
+        }
+        let e = e * PI / (m * n) as f64;
+        let harmonic_mean = if inv_r_sum > 0.0 {
+            (m * n) as f64 / inv_r_sum
+        } else {
+            f64::INFINITY
+        };
+
+        let mut rotational = [Vec3::zero(); 3];
+        let mut translational = [Vec3::zero(); 3];
+        for k in 0..n {
+            let phi = 2.0 * PI * (k as f64 + 0.5) / n as f64;
+            let phi_min = 2.0 * PI * k as f64 / n as f64;
+            let (u_k, v_k, v_k_min) = (
+                tangent(phi),
+                tangent(phi + PI / 2.0),
+                tangent(phi_min + PI / 2.0),
+            );
+            let previous = (k + n - 1) % n;
+            for j in 0..m {
+                let index = j * n + k;
+                let cos_theta = (1.0 - sin_theta[index] * sin_theta[index]).max(0.0).sqrt();
+                let tan_theta = if cos_theta > 0.0 {
+                    sin_theta[index] / cos_theta
+                } else {
+                    0.0
+                };
+                let sin_min = (j as f64 / m as f64).sqrt();
+                let cos_min = (1.0 - j as f64 / m as f64).sqrt();
+                let cos_max = (1.0 - (j + 1) as f64 / m as f64).max(0.0).sqrt();
+
+                for c in 0..3 {
+                    rotational[c] += v_k * (-tan_theta * l[index][c] * PI / (m * n) as f64);
+
+                    // Change across the boundary with the stratum below in theta.
+                    if j > 0 {
+                        let below = index - n;
+                        let dist = r[index].min(r[below]);
+                        translational[c] += u_k
+                            * (2.0 * PI / n as f64 * sin_min * cos_min * cos_min / dist
+                                * (l[index][c] - l[below][c]));
+                    }
+                    // Change across the boundary with the previous stratum in phi.
+                    let side = j * n + previous;
+                    let dist = r[index].min(r[side]);
+                    if sin_theta[index] > 0.0 {
+                        translational[c] += v_k_min
+                            * ((cos_min - cos_max) / (sin_theta[index] * dist)
+                                * (l[index][c] - l[side][c]));
+                    }
+                }
+            }
+        }
+
+        IrradianceRecord {
+            p: hit.p,
+            n: *normal,
+            e,
+            r: harmonic_mean.clamp(self.min_spacing, self.max_spacing),
+            rotational,
+            translational,
+        }
+    }
+}
+
+impl Integrator for IrradianceCacheIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        let mut l = Colour::new();
+        let mut beta = Colour::grey(1.0);
+        let mut ray = *ray;
+
+        for bounces in 0..=self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+            let material = hit.material;
+            // Only specular bounces lead here after the first hit.
+            l += beta * material.emitted(&hit, &wo);
+
+            if !material.is_delta() {
+                l += beta * uniform_sample_all_lights(&hit, &wo, scene, sampler);
+                let rho = material.diffuse_reflectance();
+                if !rho.is_black() {
+                    let n = if hit.n.dot(&wo) < 0.0 { -hit.n } else { hit.n };
+                    l += beta * rho / PI * self.irradiance(&hit, &n, scene, sampler);
+                }
+                break;
+            }
+            if bounces == self.max_depth {
+                break;
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            let Some(wi) = interaction.scattered_direction else {
+                break;
+            };
+            beta *= interaction.attenuation;
+            if beta.is_black() {
+                break;
+            }
+            ray = hit.spawn_ray(&wi);
+        }
+        l
+    }
+
+    /// Fill the cache with a first pass over the image, so that the records
+    /// do not depend on the order pixels are rendered in, then render the
+    /// image from the cache.
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let mut cache = IrradianceCache::new(scene.bounds(), self.accuracy);
+        if let Some(file) = &self.file
+            && Path::new(file).exists()
+            && let Err(err) = cache.load(Path::new(file))
+        {
+            eprintln!("Failed to load {file}: {err}");
+        }
+        self.cache.replace(cache);
+
+        let (width, height) = camera.film().resolution();
+        for y in 0..height {
+            for x in 0..width {
+                sampler.start_pixel_sample((x, y), renderer.spp);
+                if let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) {
+                    self.li(&ray, scene, sampler);
+                }
+            }
+        }
+        renderer.render_pixels(self, scene, camera, sampler);
+
+        if let Some(file) = &self.file
+            && let Err(err) = self.cache.borrow().save(Path::new(file))
+        {
+            eprintln!("Failed to write {file}: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(p: Vec3, e: f64) -> IrradianceRecord {
+        IrradianceRecord {
+            p,
+            n: Vec3::new(0.0, 0.0, 1.0),
+            e: Colour::grey(e),
+            r: 2.0,
+            rotational: [Vec3::new(0.0, 0.5, 0.0); 3],
+            translational: [Vec3::new(0.25, -0.5, 0.0); 3],
+        }
+    }
+
+    #[test]
+    fn test_weight_and_extrapolation() {
+        let record = record(Vec3::zero(), 1.0);
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(record.weight(&Vec3::zero(), &n), f64::INFINITY);
+        // Distance over the harmonic mean distance.
+        assert!((record.weight(&Vec3::new(1.0, 0.0, 0.0), &n) - 2.0).abs() < 1e-12);
+        // Normal divergence, sqrt(1 - cos 60deg).
+        let tilted = Vec3::new(0.0, 3.0_f64.sqrt() / 2.0, 0.5);
+        assert!((record.weight(&Vec3::zero(), &tilted) - 2.0_f64.sqrt()).abs() < 1e-12);
+
+        // E + (n_0 x n) . grad_r + (p - p_0) . grad_t
+        let p = Vec3::new(0.4, 0.2, 0.0);
+        assert!((record.extrapolate(&p, &n).r - (1.0 + 0.4 * 0.25 - 0.2 * 0.5)).abs() < 1e-12);
+        let tilted = Vec3::new(-0.6, 0.0, 0.8);
+        assert!((record.extrapolate(&Vec3::zero(), &tilted).g - (1.0 - 0.6 * 0.5)).abs() < 1e-12);
+        // Extrapolation never goes negative.
+        assert!(record.extrapolate(&Vec3::new(0.0, 4.0, 0.0), &n).is_black());
+    }
+
+    #[test]
+    fn test_interpolation_only_uses_records_in_range() {
+        let bounds = AABB::new(Vec3::new(-10.0, -10.0, -10.0), Vec3::new(10.0, 10.0, 10.0));
+        let mut cache = IrradianceCache::new(bounds, 0.5);
+        let mut far = record(Vec3::new(5.0, 5.0, 0.0), 3.0);
+        far.rotational = [Vec3::zero(); 3];
+        far.translational = [Vec3::zero(); 3];
+        cache.add(far);
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        assert!(cache.interpolate(&Vec3::zero(), &n).is_none());
+        let e = cache.interpolate(&Vec3::new(5.2, 5.0, 0.0), &n).unwrap();
+        assert!((e.r - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let bounds = AABB::new(Vec3::new(-10.0, -10.0, -10.0), Vec3::new(10.0, 10.0, 10.0));
+        let mut cache = IrradianceCache::new(bounds, 0.5);
+        cache.add(record(Vec3::new(0.1, 0.2, 0.3), 1.0 / 3.0));
+        cache.add(record(Vec3::new(-4.0, 2.5, 1e-7), 2.0));
+        let path =
+            std::env::temp_dir().join(format!("irradiance_cache_{}.txt", std::process::id()));
+        cache.save(&path).unwrap();
+
+        let mut loaded = IrradianceCache::new(bounds, 0.5);
+        loaded.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines = |cache: &IrradianceCache| {
+            cache
+                .records
+                .iter()
+                .map(|r| r.to_line())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(&loaded), lines(&cache));
+        let p = Vec3::new(0.2, 0.2, 0.3);
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        assert_eq!(loaded.interpolate(&p, &n), cache.interpolate(&p, &n));
+    }
+}
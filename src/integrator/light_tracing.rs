@@ -0,0 +1,144 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, pick_light, shading_normal_correction},
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::offset_origin,
+};
+
+/// Particle tracer: follows paths leaving the lights and connects every
+/// vertex to the eye, splatting the contribution onto whichever pixel it
+/// lands on. Converges to the same image as the path tracer, and handles
+/// caustics seen on diffuse surfaces well. Surfaces seen through perfect
+/// mirrors or glass cannot be connected to the eye and stay black.
+pub struct LightTracingIntegrator {
+    /// Maximum number of bounces of a light path.
+    max_depth: usize,
+    /// Number of bounces after which paths are terminated by Russian roulette.
+    rr_depth: usize,
+}
+
+impl LightTracingIntegrator {
+    pub fn new(max_depth: usize, rr_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+        }
+    }
+
+    /// Trace one light path and splat its connections to the eye.
+    fn trace_light_path(&self, scene: &Scene, camera: &mut Camera, sampler: &mut dyn Sampler) {
+        let Some((light, light_pdf)) = pick_light(scene, sampler.get_1d()) else {
+            return;
+        };
+        let Some(emission) = light.sample_le(sampler.get_2d(), sampler.get_2d()) else {
+            return;
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 || emission.le.is_black() {
+            return;
+        }
+        let is_on_surface = emission.n != Vec3::zero();
+
+        // Connect the point on the light to the eye.
+        let p = emission.ray.p;
+        if let Some(sample) = camera.sample_wi(&p) {
+            let mut l = light.l(&emission.n, &sample.wi) * sample.we
+                / (light_pdf * emission.pdf_pos * sample.pdf);
+            if is_on_surface {
+                l *= emission.n.dot(&sample.wi).abs();
+            }
+            if !l.is_black() && visible(scene, &p, &emission.n, &sample.p) {
+                camera.film_mut().add_splat(sample.raster, &l);
+            }
+        }
+
+        let cos_theta = if is_on_surface {
+            emission.n.dot(&emission.ray.d.normalize()).abs()
+        } else {
+            1.0
+        };
+        let mut beta = emission.le * cos_theta / (light_pdf * emission.pdf_pos * emission.pdf_dir);
+        let mut ray = emission.ray;
+
+        for bounces in 0..self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            // Direction towards where the light came from.
+            let wi = -ray.d.normalize();
+            let material = hit.material;
+
+            // Connect the hit to the eye, delta BSDFs cannot be connected.
+            if !material.is_delta()
+                && let Some(sample) = camera.sample_wi(&hit.p)
+            {
+                let f = material.eval(&hit, &sample.wi, &wi)
+                    * shading_normal_correction(&hit, &wi, &sample.wi);
+                let l = beta * f * hit.ns.dot(&sample.wi).abs() * sample.we / sample.pdf;
+                if !l.is_black() && visible(scene, &hit.p, &hit.n, &sample.p) {
+                    camera.film_mut().add_splat(sample.raster, &l);
+                }
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wi, sampler.get_1d(), sampler.get_2d());
+            let Some(wo) = interaction.scattered_direction else {
+                break;
+            };
+            if interaction.attenuation.is_black() {
+                break;
+            }
+            beta *= interaction.attenuation * shading_normal_correction(&hit, &wi, &wo);
+            ray = hit.spawn_ray(&wo);
+
+            if bounces >= self.rr_depth {
+                let q = (1.0 - beta.max_component()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta = beta / (1.0 - q);
+            }
+        }
+    }
+}
+
+/// Whether the eye at `eye` can see the point `p` with normal `n`.
+fn visible(scene: &Scene, p: &Vec3, n: &Vec3, eye: &Vec3) -> bool {
+    let origin = offset_origin(p, n, &(*eye - *p));
+    scene.unoccluded(&Ray::new(origin, *eye - origin))
+}
+
+impl Integrator for LightTracingIntegrator {
+    /// Light paths cannot be aimed at a given ray, so only the emission seen
+    /// directly along `ray` is returned.
+    fn li(&self, ray: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Colour {
+        scene.find_first_hit(ray).map_or(Colour::new(), |hit| {
+            hit.material.emitted(&hit, &-ray.d.normalize())
+        })
+    }
+
+    /// Trace `spp` light paths per pixel. Every pixel covers the same share
+    /// of the film, so the splats are averaged over `spp`.
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let (width, height) = camera.film().resolution();
+        for y in 0..height {
+            for x in 0..width {
+                for index in 0..renderer.spp {
+                    sampler.start_pixel_sample((x, y), index);
+                    self.trace_light_path(scene, camera, sampler);
+                }
+            }
+        }
+        camera
+            .film_mut()
+            .set_splat_scale(1.0 / renderer.spp.max(1) as f64);
+    }
+}
@@ -0,0 +1,242 @@
+pub mod ao;
+pub mod bdpt;
+pub mod debug;
+pub mod direct;
+pub mod eye_light;
+pub mod gradient;
+pub mod guiding;
+pub mod irradiance_cache;
+pub mod light_tracing;
+pub mod path;
+pub mod pssmlt;
+pub mod sppm;
+pub mod volpath;
+pub mod vpl;
+pub mod whitted;
+
+use crate::{
+    common::sampler::Sampler,
+    light::Light,
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::HitRecord,
+};
+
+/// A light transport algorithm.
+pub trait Integrator {
+    /// Estimate the radiance arriving at the origin of `ray` along its
+    /// direction.
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour;
+
+    /// Render the whole image into the camera's film. By default every pixel
+    /// is estimated independently with `li`; integrators that need to see
+    /// the camera or write to arbitrary pixels override this.
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        renderer.render_pixels(self, scene, camera, sampler);
+    }
+}
+
+/// Veach's power heuristic with exponent two.
+pub fn power_heuristic(nf: usize, f_pdf: f64, ng: usize, g_pdf: f64) -> f64 {
+    let f = nf as f64 * f_pdf;
+    let g = ng as f64 * g_pdf;
+    if f == 0.0 && g == 0.0 {
+        return 0.0;
+    }
+    (f * f) / (f * f + g * g)
+}
+
+/// Correction of the BSDF at `hit` for paths traced from the lights, which
+/// shading normals make non-symmetric (Veach 1997, section 5.3). `wo` points
+/// towards the light side of the path and `wi` towards the eye side.
+pub fn shading_normal_correction(hit: &HitRecord, wo: &Vec3, wi: &Vec3) -> f64 {
+    let denominator = hit.n.dot(wo).abs() * hit.ns.dot(wi).abs();
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    hit.ns.dot(wo).abs() * hit.n.dot(wi).abs() / denominator
+}
+
+/// Whether `hit` lies on the area light `light`.
+pub fn hit_light(hit: &HitRecord, light: &dyn Light) -> bool {
+    hit.area_light
+        .is_some_and(|area_light| std::ptr::addr_eq(area_light, light))
+}
+
+/// Estimate the light scattered towards `wo` at `hit` that arrives directly
+/// from `light`, combining a light sample and a BSDF sample with multiple
+/// importance sampling.
+pub fn estimate_direct(
+    hit: &HitRecord,
+    wo: &Vec3,
+    light: &dyn Light,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    let material = hit.material;
+    let mut ld = Colour::new();
+
+    // Sample the light.
+    if let Some(sample) = light.sample_li(&hit.p, sampler.get_2d())
+        && sample.pdf > 0.0
+        && !sample.radiance.is_black()
+    {
+        let f = material.eval(hit, wo, &sample.wi) * hit.ns.dot(&sample.wi).abs();
+        if !f.is_black() && scene.unoccluded(&hit.spawn_ray_to(&sample.p)) {
+            let weight = if light.is_delta() {
+                1.0
+            } else {
+                power_heuristic(1, sample.pdf, 1, material.pdf(hit, wo, &sample.wi))
+            };
+            ld += f * sample.radiance * weight / sample.pdf;
+        }
+    }
+
+    // Sample the BSDF, delta lights cannot be hit by chance.
+    let u = sampler.get_1d();
+    let samples = sampler.get_2d();
+    if light.is_delta() {
+        return ld;
+    }
+    let interaction = material.interact(hit, wo, u, samples);
+    let Some(wi) = interaction.scattered_direction else {
+        return ld;
+    };
+    if interaction.specular || interaction.attenuation.is_black() {
+        return ld;
+    }
+    let light_pdf = light.pdf_li(&hit.p, &wi);
+    if light_pdf == 0.0 {
+        return ld;
+    }
+    let weight = power_heuristic(1, interaction.pdf, 1, light_pdf);
+    if let Some(light_hit) = scene.find_first_hit(&hit.spawn_ray(&wi))
+        && hit_light(&light_hit, light)
+    {
+        let li = light_hit.material.emitted(&light_hit, &-wi);
+        ld += interaction.attenuation * li * weight;
+    }
+    ld
+}
+
+/// Pick one of the scene's lights uniformly with `u`, returning it with the
+/// probability it was picked with.
+pub fn pick_light(scene: &Scene, u: f64) -> Option<(&dyn Light, f64)> {
+    let lights = scene.lights();
+    if lights.is_empty() {
+        return None;
+    }
+    let index = ((u * lights.len() as f64) as usize).min(lights.len() - 1);
+    Some((lights[index].as_ref(), 1.0 / lights.len() as f64))
+}
+
+/// Estimate direct lighting at `hit` from one light picked uniformly at
+/// random.
+pub fn uniform_sample_one_light(
+    hit: &HitRecord,
+    wo: &Vec3,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    if scene.lights().is_empty() || hit.material.is_delta() {
+        return Colour::new();
+    }
+    match pick_light(scene, sampler.get_1d()) {
+        Some((light, light_pdf)) => estimate_direct(hit, wo, light, scene, sampler) / light_pdf,
+        None => Colour::new(),
+    }
+}
+
+/// Estimate direct lighting at `hit` by sampling every light once.
+pub fn uniform_sample_all_lights(
+    hit: &HitRecord,
+    wo: &Vec3,
+    scene: &Scene,
+    sampler: &mut dyn Sampler,
+) -> Colour {
+    if hit.material.is_delta() {
+        return Colour::new();
+    }
+    scene
+        .lights()
+        .iter()
+        .map(|light| estimate_direct(hit, wo, light.as_ref(), scene, sampler))
+        .fold(Colour::new(), |acc, l| acc + l)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        material::Material,
+        math::transform::Transform,
+        shape::{primitive::Primitive, triangle::Triangle},
+    };
+
+    #[test]
+    fn test_direct_lighting_agrees_with_bsdf_sampling_on_shading_normals() {
+        // A floor with tilted shading normals under a large light.
+        let ns = Vec3::new(1.0, 0.0, 1.0).normalize();
+        let floor = Triangle::new(
+            Vec3::new(-10.0, -10.0, 0.0),
+            Vec3::new(10.0, -10.0, 0.0),
+            Vec3::new(0.0, 10.0, 0.0),
+        )
+        .with_normals([ns; 3]);
+        let light = Triangle::new(
+            Vec3::new(-3.0, -3.0, 1.0),
+            Vec3::new(-3.0, 5.0, 1.0),
+            Vec3::new(5.0, -3.0, 1.0),
+        );
+        let mut scene = Scene::new();
+        let diffuse = Arc::new(Material::diffuse(Colour::grey(0.5)));
+        scene.add_primitive(Primitive::new(
+            Arc::new(floor),
+            Transform::new_identity(),
+            diffuse,
+        ));
+        let emission = Arc::new(Material::light(Colour::grey(1.0)));
+        scene.add_primitive(Primitive::new(
+            Arc::new(light),
+            Transform::new_identity(),
+            emission,
+        ));
+
+        let hit = scene
+            .find_first_hit(&Ray::new(
+                Vec3::new(0.2, 0.1, 0.5),
+                Vec3::new(0.0, 0.0, -1.0),
+            ))
+            .unwrap();
+        let wo = Vec3::new(0.0, 0.0, 1.0);
+        let light = scene.lights()[0].as_ref();
+        let mut sampler = IndependentSampler::new(7);
+        let n = 20000;
+        let (mut mis, mut bsdf) = (0.0, 0.0);
+        for index in 0..n {
+            sampler.start_pixel_sample((0, 0), index);
+            mis += estimate_direct(&hit, &wo, light, &scene, &mut sampler).luminance();
+            let interaction = hit
+                .material
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            if let Some(wi) = interaction.scattered_direction
+                && let Some(light_hit) = scene.find_first_hit(&hit.spawn_ray(&wi))
+            {
+                bsdf += (interaction.attenuation * light_hit.material.emitted(&light_hit, &-wi))
+                    .luminance();
+            }
+        }
+        let (mis, bsdf) = (mis / n as f64, bsdf / n as f64);
+        assert!((mis - bsdf).abs() < 0.02 * bsdf, "{mis} {bsdf}");
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, guiding::SdTree, uniform_sample_one_light},
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::HitRecord,
+};
+
+/// Probability of sampling the BSDF rather than the learned distribution
+/// when path guiding.
+const BSDF_SAMPLING_FRACTION: f64 = 0.5;
+
+/// Settings of the path guiding mode of `PathIntegrator`.
+#[derive(Clone, Copy, Debug)]
+pub struct GuidingConfig {
+    /// Number of training passes, pass `i` takes `2^i` samples per pixel.
+    pub iterations: usize,
+    /// Maximum size of the guiding structure in bytes.
+    pub memory_budget: usize,
+}
+
+/// A non-specular vertex of a training path, from which the radiance
+/// arriving along `wi` is recorded.
+struct GuideVertex {
+    p: Vec3,
+    wi: Vec3,
+    /// Throughput of the path including the scattering at this vertex.
+    beta: Colour,
+    /// Density `wi` was sampled with.
+    pdf: f64,
+    /// Radiance gathered by the path before leaving this vertex.
+    l: Colour,
+}
+
+/// Unidirectional path tracer with next event estimation.
+///
+/// With path guiding enabled, the radiance arriving at the path vertices is
+/// learned over training passes in an `SdTree`, and directions are sampled
+/// from a mix of the BSDF and the learned distribution.
+pub struct PathIntegrator {
+    /// Maximum number of bounces of a path.
+    max_depth: usize,
+    /// Number of bounces after which paths are terminated by Russian roulette.
+    rr_depth: usize,
+    guiding: Option<GuidingConfig>,
+}
+
+impl PathIntegrator {
+    pub fn new(max_depth: usize, rr_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+            guiding: None,
+        }
+    }
+
+    pub fn with_guiding(mut self, guiding: GuidingConfig) -> Self {
+        self.guiding = Some(guiding);
+        self
+    }
+
+    /// Radiance arriving along `ray`, sampling directions with the help of
+    /// `guide` when given. The radiance estimates of the path vertices are
+    /// pushed to `records` when given, as position, direction and value to
+    /// record in the guiding tree.
+    fn trace(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        guide: Option<&SdTree>,
+        records: Option<&mut Vec<(Vec3, Vec3, f64)>>,
+    ) -> Colour {
+        let mut l = Colour::new();
+        let mut beta = Colour::grey(1.0);
+        let mut ray = *ray;
+        let mut specular_bounce = false;
+        let mut vertices = Vec::new();
+
+        for bounces in 0.. {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+
+            // Emission is accounted for by next event estimation, except when
+            // the light can only be reached by a specular bounce.
+            if bounces == 0 || specular_bounce {
+                l += beta * hit.material.emitted(&hit, &wo);
+            }
+            if bounces >= self.max_depth {
+                break;
+            }
+
+            l += beta * uniform_sample_one_light(&hit, &wo, scene, sampler);
+
+            let (wi, weight, pdf, specular) = match guide {
+                Some(tree) if !hit.material.is_delta() && tree.can_sample(&hit.p) => {
+                    match sample_guided(&hit, &wo, tree, sampler) {
+                        Some(sample) => sample,
+                        None => break,
+                    }
+                }
+                _ => {
+                    let interaction =
+                        hit.primitive
+                            .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+                    let Some(wi) = interaction.scattered_direction else {
+                        break;
+                    };
+                    (
+                        wi,
+                        interaction.attenuation,
+                        interaction.pdf,
+                        interaction.specular,
+                    )
+                }
+            };
+            if weight.is_black() {
+                break;
+            }
+            beta *= weight;
+            specular_bounce = specular;
+            ray = hit.spawn_ray(&wi);
+            if records.is_some() && !specular {
+                vertices.push(GuideVertex {
+                    p: hit.p,
+                    wi,
+                    beta,
+                    pdf,
+                    l,
+                });
+            }
+
+            if bounces >= self.rr_depth {
+                let q = (1.0 - beta.max_component()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta = beta / (1.0 - q);
+            }
+        }
+
+        if let Some(records) = records {
+            for vertex in vertices {
+                let li = (l - vertex.l) / vertex.beta;
+                records.push((vertex.p, vertex.wi, li.luminance() / vertex.pdf));
+            }
+        }
+        l
+    }
+
+    /// Render `spp` samples per pixel sampling directions with `guide`. When
+    /// training, the radiance of the paths is recorded into `guide` instead
+    /// of the film.
+    fn render_pass(
+        &self,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+        guide: &mut SdTree,
+        (first_index, spp): (usize, usize),
+        train: bool,
+    ) {
+        let (width, height) = camera.film().resolution();
+        let mut records = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                for index in first_index..first_index + spp {
+                    sampler.start_pixel_sample((x, y), index);
+                    let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) else {
+                        continue;
+                    };
+                    let l = self.trace(
+                        &ray,
+                        scene,
+                        sampler,
+                        Some(guide),
+                        train.then_some(&mut records),
+                    );
+                    if train {
+                        for (p, wi, value) in records.drain(..) {
+                            guide.record(&p, &wi, value);
+                        }
+                    } else {
+                        camera.film_mut().add_sample((x, y), &l);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sample a direction from the BSDF or the guiding distribution with one
+/// sample multiple importance sampling, returning the direction, the
+/// throughput weight, the density and whether the scattering is specular.
+fn sample_guided(
+    hit: &HitRecord,
+    wo: &Vec3,
+    guide: &SdTree,
+    sampler: &mut dyn Sampler,
+) -> Option<(Vec3, Colour, f64, bool)> {
+    let u = sampler.get_1d();
+    let u_lobe = sampler.get_1d();
+    let samples = sampler.get_2d();
+    let material = hit.material;
+
+    let (wi, f) = if u < BSDF_SAMPLING_FRACTION {
+        let interaction = hit.primitive.interact(hit, wo, u_lobe, samples);
+        let wi = interaction.scattered_direction?;
+        if interaction.specular {
+            return Some((
+                wi,
+                interaction.attenuation / BSDF_SAMPLING_FRACTION,
+                0.0,
+                true,
+            ));
+        }
+        (wi, interaction.attenuation * interaction.pdf)
+    } else {
+        let wi = guide.sample(&hit.p, samples);
+        (wi, material.eval(hit, wo, &wi) * hit.ns.dot(&wi).abs())
+    };
+    let pdf = BSDF_SAMPLING_FRACTION * material.pdf(hit, wo, &wi)
+        + (1.0 - BSDF_SAMPLING_FRACTION) * guide.pdf(&hit.p, &wi);
+    if pdf <= 0.0 {
+        return None;
+    }
+    Some((wi, f / pdf, pdf, false))
+}
+
+impl Integrator for PathIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.trace(ray, scene, sampler, None, None)
+    }
+
+    /// With path guiding, train the guiding tree over passes of doubling
+    /// sample counts, then render the image with the learned distribution.
+    /// Training passes only feed the tree.
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let Some(guiding) = self.guiding else {
+            renderer.render_pixels(self, scene, camera, sampler);
+            return;
+        };
+        let mut guide = SdTree::new(scene.bounds());
+        // Training samples follow the ones of the final pass.
+        let mut first_index = renderer.spp;
+        for iteration in 0..guiding.iterations {
+            let spp = 1 << iteration;
+            self.render_pass(scene, camera, sampler, &mut guide, (first_index, spp), true);
+            guide.refine(iteration, guiding.memory_budget);
+            first_index += spp;
+        }
+        self.render_pass(scene, camera, sampler, &mut guide, (0, renderer.spp), false);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{
+    common::{
+        rng::Rng,
+        sampler::{MltSampler, Sampler},
+    },
+    integrator::{Integrator, path::PathIntegrator},
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+};
+
+/// Primary sample space Metropolis light transport on top of the path
+/// tracer. Markov chains explore the random numbers driving the path tracer,
+/// so that once a bright but hard to find path is sampled, nearby paths are
+/// explored by small mutations.
+pub struct PssmltIntegrator {
+    path: PathIntegrator,
+    /// Number of independent samples used to estimate the image brightness
+    /// and to seed the chains.
+    n_bootstrap: usize,
+    n_chains: usize,
+    mutations_per_pixel: usize,
+    /// Standard deviation of the small step mutations.
+    sigma: f64,
+    large_step_probability: f64,
+}
+
+impl PssmltIntegrator {
+    pub fn new(
+        path: PathIntegrator,
+        n_bootstrap: usize,
+        n_chains: usize,
+        mutations_per_pixel: usize,
+        sigma: f64,
+        large_step_probability: f64,
+    ) -> Self {
+        Self {
+            path,
+            n_bootstrap,
+            n_chains,
+            mutations_per_pixel,
+            sigma,
+            large_step_probability,
+        }
+    }
+
+    /// Radiance carried by the path the primary sample of `sampler` maps to,
+    /// and the raster position the path passes through. The first two
+    /// samples pick the position on the film.
+    fn l(&self, scene: &Scene, camera: &Camera, sampler: &mut MltSampler) -> (Colour, (f64, f64)) {
+        let (width, height) = camera.film().resolution();
+        let (u, v) = sampler.get_2d();
+        let raster = (u * width as f64, v * height as f64);
+        let pixel = (
+            (raster.0 as usize).min(width - 1),
+            (raster.1 as usize).min(height - 1),
+        );
+        let offset = (raster.0 - pixel.0 as f64, raster.1 - pixel.1 as f64);
+        let Some(ray) = camera.get_camera_sample(pixel, offset) else {
+            return (Colour::new(), raster);
+        };
+        (self.path.li(&ray, scene, sampler), raster)
+    }
+
+    fn new_sampler(&self, seed: usize) -> MltSampler {
+        MltSampler::new(seed as u64, self.sigma, self.large_step_probability)
+    }
+}
+
+/// Scalar contribution the chains sample proportionally to.
+fn contribution(l: &Colour) -> f64 {
+    l.luminance()
+}
+
+impl Integrator for PssmltIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.path.li(ray, scene, sampler)
+    }
+
+    fn render(
+        &self,
+        _renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        _sampler: &mut dyn Sampler,
+    ) {
+        let (width, height) = camera.film().resolution();
+        if width == 0
+            || height == 0
+            || self.n_bootstrap == 0
+            || self.n_chains == 0
+            || self.mutations_per_pixel == 0
+        {
+            return;
+        }
+
+        // Estimate the normalization of the image from independent samples,
+        // keeping their contributions to seed the chains.
+        let mut cdf = Vec::with_capacity(self.n_bootstrap);
+        let mut total = 0.0;
+        for index in 0..self.n_bootstrap {
+            let (l, _) = self.l(scene, camera, &mut self.new_sampler(index));
+            total += contribution(&l);
+            cdf.push(total);
+        }
+        if total <= 0.0 {
+            return;
+        }
+        let b = total / self.n_bootstrap as f64;
+
+        let n_mutations = self.mutations_per_pixel * width * height;
+        for chain in 0..self.n_chains {
+            let chain_mutations =
+                (chain + 1) * n_mutations / self.n_chains - chain * n_mutations / self.n_chains;
+            if chain_mutations == 0 {
+                continue;
+            }
+
+            // Start from a bootstrap sample picked proportionally to its
+            // contribution, replaying it from its seed.
+            let mut rng = Rng::new((self.n_bootstrap + chain) as u64);
+            let u = rng.uniform_f64() * total;
+            let index = cdf.partition_point(|&c| c <= u).min(self.n_bootstrap - 1);
+            let mut sampler = self.new_sampler(index);
+            let (mut l_current, mut p_current) = self.l(scene, camera, &mut sampler);
+
+            for _ in 0..chain_mutations {
+                sampler.start_iteration();
+                let (l_proposed, p_proposed) = self.l(scene, camera, &mut sampler);
+                let c_current = contribution(&l_current);
+                let c_proposed = contribution(&l_proposed);
+                let accept = if c_current > 0.0 {
+                    (c_proposed / c_current).min(1.0)
+                } else {
+                    1.0
+                };
+
+                // Splat both samples weighted by their expected acceptance.
+                let film = camera.film_mut();
+                if accept > 0.0 && c_proposed > 0.0 {
+                    film.add_splat(p_proposed, &(l_proposed * accept / c_proposed));
+                }
+                if accept < 1.0 && c_current > 0.0 {
+                    film.add_splat(p_current, &(l_current * (1.0 - accept) / c_current));
+                }
+
+                if rng.uniform_f64() < accept {
+                    l_current = l_proposed;
+                    p_current = p_proposed;
+                    sampler.accept();
+                } else {
+                    sampler.reject();
+                }
+            }
+        }
+        camera
+            .film_mut()
+            .set_splat_scale(b / self.mutations_per_pixel as f64);
+    }
+}
@@ -0,0 +1,474 @@
+use std::f64::consts::PI;
+
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, pick_light, shading_normal_correction, uniform_sample_one_light},
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::HitRecord,
+};
+
+/// Fraction of the new photons kept when the gather radius shrinks.
+const ALPHA: f64 = 2.0 / 3.0;
+
+/// Where a camera path first reached a non-specular surface.
+struct VisiblePoint<'a> {
+    hit: HitRecord<'a>,
+    wo: Vec3,
+    /// Throughput of the camera path up to the hit.
+    beta: Colour,
+}
+
+/// Per pixel statistics of the progressive estimate.
+struct SppmPixel<'a> {
+    radius: f64,
+    /// Direct lighting summed over all iterations.
+    ld: Colour,
+    vp: Option<VisiblePoint<'a>>,
+    /// Flux deposited by the photons of the current iteration.
+    phi: Colour,
+    /// Number of photons gathered during the current iteration.
+    m: usize,
+    /// Accumulated photon count after the radius reductions.
+    n: f64,
+    /// Flux accumulated over all iterations, scaled to the current radius.
+    tau: Colour,
+}
+
+impl SppmPixel<'_> {
+    fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            ld: Colour::new(),
+            vp: None,
+            phi: Colour::new(),
+            m: 0,
+            n: 0.0,
+            tau: Colour::new(),
+        }
+    }
+
+    /// Fold the photons of the current iteration into the estimate: shrink
+    /// the radius of a pixel that gathered photons and rescale its flux to
+    /// the new radius.
+    fn update(&mut self) {
+        if self.m == 0 {
+            return;
+        }
+        let n_new = self.n + ALPHA * self.m as f64;
+        let radius_new = self.radius * (n_new / (self.n + self.m as f64)).sqrt();
+        let beta = self.vp.as_ref().map_or(Colour::new(), |vp| vp.beta);
+        self.tau =
+            (self.tau + beta * self.phi) * (radius_new * radius_new) / (self.radius * self.radius);
+        self.n = n_new;
+        self.radius = radius_new;
+        self.m = 0;
+        self.phi = Colour::new();
+    }
+}
+
+/// Uniform grid over the visible points, hashed into a fixed number of
+/// buckets. Every visible point is stored in all the cells its gather
+/// sphere overlaps.
+struct PhotonGrid {
+    min: Vec3,
+    max: Vec3,
+    resolution: [usize; 3],
+    buckets: Vec<Vec<usize>>,
+}
+
+impl PhotonGrid {
+    fn new(pixels: &[SppmPixel]) -> Self {
+        let mut min = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+        let mut max_radius: f64 = 0.0;
+        for pixel in pixels {
+            let Some(vp) = &pixel.vp else {
+                continue;
+            };
+            for i in 0..3 {
+                min[i] = min[i].min(vp.hit.p[i] - pixel.radius);
+                max[i] = max[i].max(vp.hit.p[i] + pixel.radius);
+            }
+            max_radius = max_radius.max(pixel.radius);
+        }
+
+        let mut grid = Self {
+            min,
+            max,
+            resolution: [1; 3],
+            buckets: vec![Vec::new(); pixels.len().max(1)],
+        };
+        if max_radius == 0.0 {
+            return grid;
+        }
+        let diagonal = max - min;
+        let max_extent = diagonal.x.max(diagonal.y).max(diagonal.z);
+        let base_resolution = (max_extent / max_radius) as usize;
+        for i in 0..3 {
+            grid.resolution[i] =
+                ((base_resolution as f64 * diagonal[i] / max_extent) as usize).max(1);
+        }
+
+        for (index, pixel) in pixels.iter().enumerate() {
+            let Some(vp) = &pixel.vp else {
+                continue;
+            };
+            let radius = Vec3::new(pixel.radius, pixel.radius, pixel.radius);
+            let lo = grid.cell(&(vp.hit.p - radius));
+            let hi = grid.cell(&(vp.hit.p + radius));
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        // Cells of the same point may share a bucket, store
+                        // it once so that photons are not counted twice.
+                        let hash = grid.hash([x, y, z]);
+                        let bucket = &mut grid.buckets[hash];
+                        if bucket.last() != Some(&index) {
+                            bucket.push(index);
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    /// The cell containing `p`, clamped to the grid.
+    fn cell(&self, p: &Vec3) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for i in 0..3 {
+            let extent = self.max[i] - self.min[i];
+            let offset = if extent > 0.0 {
+                (p[i] - self.min[i]) / extent
+            } else {
+                0.0
+            };
+            let c = (offset * self.resolution[i] as f64).max(0.0) as usize;
+            cell[i] = c.min(self.resolution[i] - 1);
+        }
+        cell
+    }
+
+    fn contains(&self, p: &Vec3) -> bool {
+        (0..3).all(|i| self.min[i] <= p[i] && p[i] <= self.max[i])
+    }
+
+    fn hash(&self, cell: [usize; 3]) -> usize {
+        let h = (cell[0].wrapping_mul(73856093))
+            ^ (cell[1].wrapping_mul(19349663))
+            ^ (cell[2].wrapping_mul(83492791));
+        h % self.buckets.len()
+    }
+
+    /// Indices of the pixels whose visible points may be close to `p`.
+    fn lookup(&self, p: &Vec3) -> &[usize] {
+        if !self.contains(p) {
+            return &[];
+        }
+        &self.buckets[self.hash(self.cell(p))]
+    }
+}
+
+/// Stochastic progressive photon mapping. Every iteration traces one camera
+/// path per pixel up to its first non-specular hit, then shoots photons from
+/// the lights and gathers those that land within a shrinking radius of the
+/// camera hits. Handles caustics and other paths that are hard to reach
+/// from the camera.
+pub struct SppmIntegrator {
+    /// Number of camera and photon passes.
+    iterations: usize,
+    /// Number of photons traced per iteration.
+    photons_per_iteration: usize,
+    /// Gather radius at the first iteration.
+    initial_radius: f64,
+    /// Maximum number of bounces of camera and photon paths.
+    max_depth: usize,
+}
+
+impl SppmIntegrator {
+    pub fn new(
+        iterations: usize,
+        photons_per_iteration: usize,
+        initial_radius: f64,
+        max_depth: usize,
+    ) -> Self {
+        Self {
+            iterations,
+            photons_per_iteration,
+            initial_radius,
+            max_depth,
+        }
+    }
+
+    /// Follow a camera ray through specular bounces, accumulating direct
+    /// lighting into `pixel` and recording where it lands on a surface that
+    /// photons can be gathered on.
+    fn trace_camera_path<'a>(
+        &self,
+        ray: &Ray,
+        scene: &'a Scene,
+        sampler: &mut dyn Sampler,
+        pixel: &mut SppmPixel<'a>,
+    ) {
+        let mut beta = Colour::grey(1.0);
+        let mut ray = *ray;
+        let mut specular_bounce = false;
+        for depth in 0..self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+            if depth == 0 || specular_bounce {
+                pixel.ld += beta * hit.material.emitted(&hit, &wo);
+            }
+            pixel.ld += beta * uniform_sample_one_light(&hit, &wo, scene, sampler);
+
+            if !hit.material.is_delta() {
+                pixel.vp = Some(VisiblePoint { hit, wo, beta });
+                break;
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            let Some(wi) = interaction.scattered_direction else {
+                break;
+            };
+            if interaction.attenuation.is_black() {
+                break;
+            }
+            beta *= interaction.attenuation;
+            specular_bounce = interaction.specular;
+            ray = hit.spawn_ray(&wi);
+        }
+    }
+
+    /// Shoot one photon from a light and deposit its flux at every visible
+    /// point it passes close to after the first bounce.
+    fn trace_photon(
+        &self,
+        scene: &Scene,
+        sampler: &mut dyn Sampler,
+        grid: &PhotonGrid,
+        pixels: &mut [SppmPixel],
+    ) {
+        let Some((light, light_pdf)) = pick_light(scene, sampler.get_1d()) else {
+            return;
+        };
+        let Some(emission) = light.sample_le(sampler.get_2d(), sampler.get_2d()) else {
+            return;
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 || emission.le.is_black() {
+            return;
+        }
+        let cos_theta = if emission.n == Vec3::zero() {
+            1.0
+        } else {
+            emission.n.dot(&emission.ray.d.normalize()).abs()
+        };
+        let mut beta = emission.le * cos_theta / (light_pdf * emission.pdf_pos * emission.pdf_dir);
+        let mut ray = emission.ray;
+
+        for depth in 0..self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+
+            // Direct lighting is already estimated from the camera.
+            if depth > 0 {
+                for &index in grid.lookup(&hit.p) {
+                    let pixel = &mut pixels[index];
+                    let Some(vp) = &pixel.vp else {
+                        continue;
+                    };
+                    if (vp.hit.p - hit.p).length_squared() > pixel.radius * pixel.radius {
+                        continue;
+                    }
+                    let f = vp.hit.material.eval(&vp.hit, &vp.wo, &wo);
+                    pixel.phi += beta * f;
+                    pixel.m += 1;
+                }
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            let Some(wi) = interaction.scattered_direction else {
+                break;
+            };
+            if interaction.attenuation.is_black() {
+                break;
+            }
+            let beta_new =
+                beta * interaction.attenuation * shading_normal_correction(&hit, &wo, &wi);
+
+            // Terminate photons whose throughput dropped the most.
+            let q = (1.0 - beta_new.luminance() / beta.luminance()).max(0.0);
+            if sampler.get_1d() < q {
+                break;
+            }
+            beta = beta_new / (1.0 - q);
+            ray = hit.spawn_ray(&wi);
+        }
+    }
+
+    /// Radiance estimate of `pixel` after `iterations` passes.
+    fn estimate(&self, pixel: &SppmPixel, iterations: usize) -> Colour {
+        let iterations = iterations.max(1);
+        let photons = (iterations * self.photons_per_iteration) as f64;
+        let mut l = pixel.ld / iterations as f64;
+        if photons > 0.0 && pixel.radius > 0.0 {
+            l += pixel.tau / (photons * PI * pixel.radius * pixel.radius);
+        }
+        l
+    }
+}
+
+impl Integrator for SppmIntegrator {
+    /// Photon mapping needs the whole image, so a single ray only gets the
+    /// direct lighting at its first non-specular hit.
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        let mut pixel = SppmPixel::new(self.initial_radius);
+        self.trace_camera_path(ray, scene, sampler, &mut pixel);
+        pixel.ld
+    }
+
+    fn render(
+        &self,
+        _renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let (width, height) = camera.film().resolution();
+        let mut pixels: Vec<SppmPixel> = (0..width * height)
+            .map(|_| SppmPixel::new(self.initial_radius))
+            .collect();
+
+        for iteration in 0..self.iterations {
+            for y in 0..height {
+                for x in 0..width {
+                    sampler.start_pixel_sample((x, y), iteration);
+                    let pixel = &mut pixels[y * width + x];
+                    pixel.vp = None;
+                    let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) else {
+                        continue;
+                    };
+                    self.trace_camera_path(&ray, scene, sampler, pixel);
+                }
+            }
+
+            let grid = PhotonGrid::new(&pixels);
+            for _ in 0..self.photons_per_iteration {
+                self.trace_photon(scene, sampler, &grid, &mut pixels);
+            }
+
+            for pixel in &mut pixels {
+                pixel.update();
+            }
+
+            let mut film = camera.film().sibling(&format!("iter{:04}", iteration + 1));
+            for y in 0..height {
+                for x in 0..width {
+                    film.add_sample(
+                        (x, y),
+                        &self.estimate(&pixels[y * width + x], iteration + 1),
+                    );
+                }
+            }
+            if let Err(err) = film.save() {
+                eprintln!("Failed to write {}: {err}", film.filename);
+            }
+        }
+
+        let film = camera.film_mut();
+        for y in 0..height {
+            for x in 0..width {
+                film.add_sample(
+                    (x, y),
+                    &self.estimate(&pixels[y * width + x], self.iterations),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        material::Material,
+        math::transform::Transform,
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    fn unit_sphere() -> Scene {
+        let mut scene = Scene::new();
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::new_identity(),
+            Arc::new(Material::diffuse(Colour::grey(0.5))),
+        ));
+        scene
+    }
+
+    /// A pixel whose visible point is where `ray` hits `scene`.
+    fn pixel_at<'a>(scene: &'a Scene, ray: &Ray, radius: f64, beta: Colour) -> SppmPixel<'a> {
+        let hit = scene.find_first_hit(ray).unwrap();
+        let mut pixel = SppmPixel::new(radius);
+        pixel.vp = Some(VisiblePoint {
+            hit,
+            wo: -ray.d,
+            beta,
+        });
+        pixel
+    }
+
+    #[test]
+    fn test_update_shrinks_radius_and_rescales_flux() {
+        let scene = unit_sphere();
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut pixel = pixel_at(&scene, &ray, 1.0, Colour::grey(0.5));
+
+        // N = 0 + 2/3 * 3, r^2 = 1 * 2 / 3, tau = 0.5 * 2 * r^2.
+        pixel.m = 3;
+        pixel.phi = Colour::grey(2.0);
+        pixel.update();
+        assert!((pixel.n - 2.0).abs() < 1e-12);
+        assert!((pixel.radius * pixel.radius - 2.0 / 3.0).abs() < 1e-12);
+        assert!((pixel.tau.r - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!((pixel.m, pixel.phi), (0, Colour::new()));
+
+        // An iteration without photons leaves the pixel alone.
+        pixel.update();
+        assert!((pixel.radius * pixel.radius - 2.0 / 3.0).abs() < 1e-12);
+
+        // N = 2 + 2/3 * 3, r^2 = 2/3 * 4/5, tau = (2/3 + 0.5 * 2) * 4/5.
+        pixel.m = 3;
+        pixel.phi = Colour::grey(2.0);
+        pixel.update();
+        assert!((pixel.n - 4.0).abs() < 1e-12);
+        assert!((pixel.radius * pixel.radius - 8.0 / 15.0).abs() < 1e-12);
+        assert!((pixel.tau.r - 4.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_grid_finds_photons_within_radius() {
+        let scene = unit_sphere();
+        let pixels = vec![pixel_at(
+            &scene,
+            &Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)),
+            0.1,
+            Colour::grey(1.0),
+        )];
+        let grid = PhotonGrid::new(&pixels);
+        assert_eq!(grid.lookup(&Vec3::new(0.05, -0.05, 1.0)), &[0]);
+        assert!(grid.lookup(&Vec3::new(0.0, 0.15, 1.0)).is_empty());
+        assert!(grid.lookup(&Vec3::new(0.0, 0.0, -1.0)).is_empty());
+    }
+}
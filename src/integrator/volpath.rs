@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, hit_light, pick_light, power_heuristic},
+    math::vec3::Vec3,
+    medium::{Medium, phase::HenyeyGreenstein},
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+    shape::{HitRecord, RAY_EPSILON, offset_origin},
+};
+
+/// Where light is scattered along a path.
+enum Scatter<'a, 'b> {
+    Surface(&'b HitRecord<'a>),
+    Medium(&'b HenyeyGreenstein),
+}
+
+impl Scatter<'_, '_> {
+    /// Scattered light for light arriving from `wi`, including the cosine
+    /// factor at surfaces.
+    fn f(&self, wo: &Vec3, wi: &Vec3) -> Colour {
+        match self {
+            Scatter::Surface(hit) => hit.material.eval(hit, wo, wi) * hit.ns.dot(wi).abs(),
+            Scatter::Medium(phase) => Colour::grey(phase.p(wo, wi)),
+        }
+    }
+
+    fn pdf(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        match self {
+            Scatter::Surface(hit) => hit.material.pdf(hit, wo, wi),
+            Scatter::Medium(phase) => phase.p(wo, wi),
+        }
+    }
+}
+
+/// Path tracer for scenes with participating media. Free-flight distances
+/// are sampled in the medium a ray travels through, and scattering in the
+/// medium follows its phase function. Next event estimation accounts for
+/// the transmittance of the media along shadow rays.
+///
+/// Surfaces with an interface material only switch the medium. Without
+/// media this estimates the same image as `PathIntegrator`.
+pub struct VolPathIntegrator {
+    /// Maximum number of scattering events of a path.
+    max_depth: usize,
+    /// Number of scattering events after which paths are terminated by
+    /// Russian roulette.
+    rr_depth: usize,
+}
+
+impl VolPathIntegrator {
+    pub fn new(max_depth: usize, rr_depth: usize) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+        }
+    }
+
+    /// Randomly terminate the path after `rr_depth` scattering events,
+    /// returning whether it survives.
+    fn russian_roulette(
+        &self,
+        beta: &mut Colour,
+        bounces: usize,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        if bounces <= self.rr_depth {
+            return true;
+        }
+        let q = (1.0 - beta.max_component()).max(0.05);
+        if sampler.get_1d() < q {
+            return false;
+        }
+        *beta = *beta / (1.0 - q);
+        true
+    }
+
+    /// Direct lighting scattered towards `wo` at `p` from one light picked at
+    /// random, combining a light sample and a sample of the BSDF or phase
+    /// function with multiple importance sampling.
+    #[allow(clippy::too_many_arguments)]
+    fn sample_one_light(
+        &self,
+        scene: &Scene,
+        p: &Vec3,
+        n: &Vec3,
+        wo: &Vec3,
+        scatter: &Scatter,
+        medium: &Option<Arc<dyn Medium>>,
+        sampler: &mut dyn Sampler,
+    ) -> Colour {
+        let Some((light, light_pick_pdf)) = pick_light(scene, sampler.get_1d()) else {
+            return Colour::new();
+        };
+        let mut ld = Colour::new();
+
+        // Sample the light.
+        if let Some(sample) = light.sample_li(p, sampler.get_2d())
+            && sample.pdf > 0.0
+            && !sample.radiance.is_black()
+        {
+            let f = scatter.f(wo, &sample.wi);
+            if !f.is_black() {
+                let origin = offset_origin(p, n, &(sample.p - *p));
+                let ray = Ray::new(origin, sample.p - origin);
+                let medium = scatter_medium(scatter, &sample.wi, medium);
+                let (tr, blocker) =
+                    trace_transmittance(scene, &ray, 1.0 - RAY_EPSILON, medium, sampler);
+                if blocker.is_none() && !tr.is_black() {
+                    let weight = if light.is_delta() {
+                        1.0
+                    } else {
+                        power_heuristic(1, sample.pdf, 1, scatter.pdf(wo, &sample.wi))
+                    };
+                    ld += f * tr * sample.radiance * weight / sample.pdf;
+                }
+            }
+        }
+
+        // Sample the BSDF or the phase function, delta lights cannot be hit
+        // by chance.
+        let u = sampler.get_1d();
+        let samples = sampler.get_2d();
+        if light.is_delta() {
+            return ld / light_pick_pdf;
+        }
+        let (wi, weight, pdf) = match scatter {
+            Scatter::Surface(hit) => {
+                let interaction = hit.primitive.interact(hit, wo, u, samples);
+                match interaction.scattered_direction {
+                    Some(wi) if !interaction.specular && !interaction.attenuation.is_black() => {
+                        (wi, interaction.attenuation, interaction.pdf)
+                    }
+                    _ => return ld / light_pick_pdf,
+                }
+            }
+            Scatter::Medium(phase) => {
+                let (wi, pdf) = phase.sample_p(wo, samples);
+                (wi, Colour::grey(1.0), pdf)
+            }
+        };
+        let light_pdf = light.pdf_li(p, &wi);
+        if light_pdf > 0.0 {
+            let origin = offset_origin(p, n, &wi);
+            let medium = scatter_medium(scatter, &wi, medium);
+            let (tr, light_hit) =
+                trace_transmittance(scene, &Ray::new(origin, wi), f64::INFINITY, medium, sampler);
+            if let Some(light_hit) = light_hit
+                && hit_light(&light_hit, light)
+            {
+                let li = light_hit.material.emitted(&light_hit, &-wi);
+                ld += weight * tr * li * power_heuristic(1, pdf, 1, light_pdf);
+            }
+        }
+        ld / light_pick_pdf
+    }
+}
+
+impl Integrator for VolPathIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        let mut l = Colour::new();
+        let mut beta = Colour::grey(1.0);
+        let mut ray = *ray;
+        let mut medium = scene.medium().cloned();
+        let mut specular_bounce = false;
+        let mut bounces = 0;
+
+        loop {
+            let hit = scene.find_first_hit(&ray);
+
+            // Scatter in the medium before reaching the surface.
+            if let Some(m) = &medium {
+                let t_max = hit.map_or(f64::INFINITY, |hit| hit.t);
+                let sample = m.sample(&ray, t_max, sampler);
+                beta *= sample.weight;
+                if beta.is_black() {
+                    break;
+                }
+                if let Some(t) = sample.t {
+                    if bounces >= self.max_depth {
+                        break;
+                    }
+                    let p = ray.at(&t);
+                    let wo = -ray.d.normalize();
+                    let phase = *m.phase();
+                    let scatter = Scatter::Medium(&phase);
+                    l += beta
+                        * self.sample_one_light(
+                            scene,
+                            &p,
+                            &Vec3::zero(),
+                            &wo,
+                            &scatter,
+                            &medium,
+                            sampler,
+                        );
+
+                    // The phase function is sampled exactly, so the throughput
+                    // does not change.
+                    let (wi, _) = phase.sample_p(&wo, sampler.get_2d());
+                    ray = Ray::new(p, wi);
+                    specular_bounce = false;
+                    bounces += 1;
+                    if !self.russian_roulette(&mut beta, bounces, sampler) {
+                        break;
+                    }
+                    continue;
+                }
+            }
+
+            let Some(hit) = hit else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+            let material = hit.material;
+
+            // Medium boundaries do not scatter, only change the medium.
+            if material.is_interface() {
+                medium = next_medium(&hit, &ray.d, medium);
+                ray = hit.spawn_ray(&ray.d);
+                continue;
+            }
+
+            // Emission is accounted for by next event estimation, except when
+            // the light can only be reached by a specular bounce.
+            if bounces == 0 || specular_bounce {
+                l += beta * material.emitted(&hit, &wo);
+            }
+            if bounces >= self.max_depth {
+                break;
+            }
+
+            if !material.is_delta() {
+                let scatter = Scatter::Surface(&hit);
+                l += beta
+                    * self.sample_one_light(scene, &hit.p, &hit.n, &wo, &scatter, &medium, sampler);
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            let Some(wi) = interaction.scattered_direction else {
+                break;
+            };
+            if interaction.attenuation.is_black() {
+                break;
+            }
+            beta *= interaction.attenuation;
+            specular_bounce = interaction.specular;
+            medium = next_medium(&hit, &wi, medium);
+            ray = hit.spawn_ray(&wi);
+            bounces += 1;
+            if !self.russian_roulette(&mut beta, bounces, sampler) {
+                break;
+            }
+        }
+        l
+    }
+}
+
+/// The medium a ray leaving `hit` in direction `d` travels through.
+fn next_medium(
+    hit: &HitRecord,
+    d: &Vec3,
+    current: Option<Arc<dyn Medium>>,
+) -> Option<Arc<dyn Medium>> {
+    match &hit.primitive.medium_interface {
+        Some(medium_interface) => medium_interface.medium(&hit.n, d),
+        None => current,
+    }
+}
+
+fn scatter_medium(
+    scatter: &Scatter,
+    d: &Vec3,
+    current: &Option<Arc<dyn Medium>>,
+) -> Option<Arc<dyn Medium>> {
+    match scatter {
+        Scatter::Surface(hit) => next_medium(hit, d, current.clone()),
+        Scatter::Medium(_) => current.clone(),
+    }
+}
+
+/// Follow `ray` through medium boundaries up to `t_end`, returning the
+/// transmittance of the media it crossed and the first opaque surface it
+/// reached, if any.
+fn trace_transmittance<'a>(
+    scene: &'a Scene,
+    ray: &Ray,
+    mut t_end: f64,
+    mut medium: Option<Arc<dyn Medium>>,
+    sampler: &mut dyn Sampler,
+) -> (Colour, Option<HitRecord<'a>>) {
+    let mut tr = Colour::grey(1.0);
+    let mut ray = *ray;
+    loop {
+        let hit = scene.find_first_hit(&ray).filter(|hit| hit.t < t_end);
+        let t_max = hit.map_or(t_end, |hit| hit.t);
+        if let Some(m) = &medium {
+            tr *= m.tr(&ray, t_max, sampler);
+            if tr.is_black() {
+                return (tr, None);
+            }
+        }
+        let Some(hit) = hit else {
+            return (tr, None);
+        };
+        if !hit.material.is_interface() {
+            return (tr, Some(hit));
+        }
+        medium = next_medium(&hit, &ray.d, medium);
+        ray = hit.spawn_ray(&ray.d);
+        t_end -= hit.t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        integrator::path::PathIntegrator,
+        material::Material,
+        math::{matrix4::Matrix4, transform::Transform},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    /// Average radiance `integrator` estimates along `ray`.
+    fn mean_li(integrator: &dyn Integrator, scene: &Scene, ray: &Ray, n: usize) -> f64 {
+        let mut sampler = IndependentSampler::new(9);
+        let mut l = 0.0;
+        for index in 0..n {
+            sampler.start_pixel_sample((0, 0), index);
+            l += integrator.li(ray, scene, &mut sampler).luminance();
+        }
+        l / n as f64
+    }
+
+    #[test]
+    fn test_matches_path_tracing_without_media() {
+        // A diffuse sphere on a floor under a spherical light.
+        let mut scene = Scene::new();
+        let diffuse = Arc::new(Material::diffuse(Colour::grey(0.6)));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, -1000.0])),
+            diffuse.clone(),
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, 1.0])),
+            diffuse,
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.5)),
+            Transform::from_matrix(Matrix4::new_translate([1.5, -1.5, 3.0])),
+            Arc::new(Material::light(Colour::grey(10.0))),
+        ));
+
+        let n = 20000;
+        for target in [Vec3::new(0.0, 0.0, 1.0), Vec3::new(-1.5, -0.5, 0.0)] {
+            let origin = Vec3::new(0.0, -6.0, 2.0);
+            let ray = Ray::new(origin, (target - origin).normalize());
+            let volpath = mean_li(&VolPathIntegrator::new(4, 3), &scene, &ray, n);
+            let path = mean_li(&PathIntegrator::new(4, 3), &scene, &ray, n);
+            assert!(path > 0.0);
+            assert!((volpath - path).abs() < 0.03 * path, "{volpath} {path}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::{Integrator, pick_light, shading_normal_correction, uniform_sample_all_lights},
+    math::vec3::Vec3,
+    render::{camera::Camera, colour::Colour, ray::Ray, renderer::Renderer},
+    scene::Scene,
+    shape::{HitRecord, offset_origin},
+};
+
+/// Light arriving at a diffuse vertex of a light path, reflected from there
+/// as if it was a point light.
+struct Vpl<'a> {
+    hit: HitRecord<'a>,
+    /// Direction towards where the light came from.
+    wi: Vec3,
+    /// Throughput of the light path, divided by the number of paths.
+    beta: Colour,
+}
+
+/// Instant radiosity (Keller 1997). Light paths are traced before
+/// rendering, and virtual point lights are deposited where they hit
+/// non-specular surfaces. Camera rays follow specular bounces, and at the
+/// first other surface they hit gather the direct lighting and the light
+/// reflected by every visible virtual point light.
+///
+/// The squared distance to the virtual lights is clamped, trading the
+/// bright splotches around them for missing light in corners.
+pub struct VplIntegrator {
+    /// Maximum number of bounces of the light and camera paths.
+    max_depth: usize,
+    rr_depth: usize,
+    /// Number of light paths traced.
+    n_paths: usize,
+    /// Distances to the virtual lights below this are clamped.
+    min_distance: f64,
+}
+
+impl VplIntegrator {
+    pub fn new(max_depth: usize, rr_depth: usize, n_paths: usize, min_distance: f64) -> Self {
+        Self {
+            max_depth,
+            rr_depth,
+            n_paths,
+            min_distance,
+        }
+    }
+
+    /// Trace a light path and deposit a virtual light at each of its
+    /// non-specular vertices.
+    fn trace_light_path<'a>(
+        &self,
+        scene: &'a Scene,
+        sampler: &mut dyn Sampler,
+        vpls: &mut Vec<Vpl<'a>>,
+    ) {
+        let Some((light, light_pdf)) = pick_light(scene, sampler.get_1d()) else {
+            return;
+        };
+        let Some(emission) = light.sample_le(sampler.get_2d(), sampler.get_2d()) else {
+            return;
+        };
+        if emission.pdf_pos == 0.0 || emission.pdf_dir == 0.0 || emission.le.is_black() {
+            return;
+        }
+        let cos_theta = if emission.n != Vec3::zero() {
+            emission.n.dot(&emission.ray.d.normalize()).abs()
+        } else {
+            1.0
+        };
+        let mut beta = emission.le * cos_theta
+            / (light_pdf * emission.pdf_pos * emission.pdf_dir * self.n_paths as f64);
+        let mut ray = emission.ray;
+
+        for bounces in 0..self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wi = -ray.d.normalize();
+            if !hit.material.is_delta() {
+                vpls.push(Vpl { hit, wi, beta });
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wi, sampler.get_1d(), sampler.get_2d());
+            let Some(wo) = interaction.scattered_direction else {
+                break;
+            };
+            beta *= interaction.attenuation * shading_normal_correction(&hit, &wi, &wo);
+            if beta.is_black() {
+                break;
+            }
+            ray = hit.spawn_ray(&wo);
+
+            if bounces >= self.rr_depth {
+                let q = (1.0 - beta.max_component()).max(0.05);
+                if sampler.get_1d() < q {
+                    break;
+                }
+                beta = beta / (1.0 - q);
+            }
+        }
+    }
+
+    /// Light reflected towards `wo` at `hit` from the virtual lights.
+    fn gather(&self, hit: &HitRecord, wo: &Vec3, scene: &Scene, vpls: &[Vpl]) -> Colour {
+        let material = hit.material;
+        let mut l = Colour::new();
+        for vpl in vpls {
+            let d = vpl.hit.p - hit.p;
+            let dist2 = d.length_squared();
+            if dist2 == 0.0 {
+                continue;
+            }
+            let w = d / dist2.sqrt();
+            let f = material.eval(hit, wo, &w)
+                * vpl.hit.material.eval(&vpl.hit, &-w, &vpl.wi)
+                * shading_normal_correction(&vpl.hit, &vpl.wi, &-w);
+            if f.is_black() {
+                continue;
+            }
+            let g = hit.ns.dot(&w).abs() * vpl.hit.ns.dot(&w).abs()
+                / dist2.max(self.min_distance * self.min_distance);
+            let target = offset_origin(&vpl.hit.p, &vpl.hit.n, &-d);
+            if scene.unoccluded(&hit.spawn_ray_to(&target)) {
+                l += f * g * vpl.beta;
+            }
+        }
+        l
+    }
+
+    /// Radiance along `ray` lit by the lights and by `vpls`.
+    fn li_vpl(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler, vpls: &[Vpl]) -> Colour {
+        let mut l = Colour::new();
+        let mut beta = Colour::grey(1.0);
+        let mut ray = *ray;
+
+        for bounces in 0..=self.max_depth {
+            let Some(hit) = scene.find_first_hit(&ray) else {
+                break;
+            };
+            let wo = -ray.d.normalize();
+            let material = hit.material;
+            // Only specular bounces lead here after the first hit.
+            l += beta * material.emitted(&hit, &wo);
+
+            if !material.is_delta() {
+                l += beta * uniform_sample_all_lights(&hit, &wo, scene, sampler);
+                l += beta * self.gather(&hit, &wo, scene, vpls);
+                break;
+            }
+            if bounces == self.max_depth {
+                break;
+            }
+
+            let interaction = hit
+                .primitive
+                .interact(&hit, &wo, sampler.get_1d(), sampler.get_2d());
+            let Some(wi) = interaction.scattered_direction else {
+                break;
+            };
+            beta *= interaction.attenuation;
+            if beta.is_black() {
+                break;
+            }
+            ray = hit.spawn_ray(&wi);
+        }
+        l
+    }
+}
+
+impl Integrator for VplIntegrator {
+    /// Without the virtual lights traced by `render`, only direct lighting.
+    fn li(&self, ray: &Ray, scene: &Scene, sampler: &mut dyn Sampler) -> Colour {
+        self.li_vpl(ray, scene, sampler, &[])
+    }
+
+    fn render(
+        &self,
+        renderer: &Renderer,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        // Light paths use the sample indices past the camera samples of the
+        // first pixel.
+        let mut vpls = Vec::new();
+        for index in 0..self.n_paths {
+            sampler.start_pixel_sample((0, 0), renderer.spp + index);
+            self.trace_light_path(scene, sampler, &mut vpls);
+        }
+
+        let (width, height) = camera.film().resolution();
+        for y in 0..height {
+            for x in 0..width {
+                for index in 0..renderer.spp {
+                    sampler.start_pixel_sample((x, y), index);
+                    let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) else {
+                        continue;
+                    };
+                    let l = self.li_vpl(&ray, scene, sampler, &vpls);
+                    camera.film_mut().add_sample((x, y), &l);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        integrator::path::PathIntegrator,
+        material::Material,
+        math::{matrix4::Matrix4, transform::Transform},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    /// Average radiance `integrator` estimates along `ray`.
+    fn mean_li(integrator: &dyn Integrator, scene: &Scene, ray: &Ray, n: usize) -> f64 {
+        let mut sampler = IndependentSampler::new(2);
+        let mut l = 0.0;
+        for index in 0..n {
+            sampler.start_pixel_sample((0, 0), index);
+            l += integrator.li(ray, scene, &mut sampler).luminance();
+        }
+        l / n as f64
+    }
+
+    #[test]
+    fn test_unclamped_gather_matches_path_traced_indirect() {
+        // A floor and a wall, approximated by large spheres, lit by a small
+        // light so that the floor next to the wall receives light reflected
+        // by the wall.
+        let mut scene = Scene::new();
+        let diffuse = Arc::new(Material::diffuse(Colour::grey(0.7)));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, -1000.0])),
+            diffuse.clone(),
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            Transform::from_matrix(Matrix4::new_translate([1001.0, 0.0, 0.0])),
+            diffuse,
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.5)),
+            Transform::from_matrix(Matrix4::new_translate([-1.0, 0.0, 3.0])),
+            Arc::new(Material::light(Colour::grey(20.0))),
+        ));
+        let ray = Ray::new(
+            Vec3::new(-2.0, 0.5, 1.0),
+            Vec3::new(2.0, -0.5, -1.0).normalize(),
+        );
+
+        // One bounce of indirect light, from virtual lights on the first
+        // vertex of the light paths.
+        let n_paths = 50000;
+        let integrator = VplIntegrator::new(1, 10, n_paths, 0.0);
+        let mut sampler = IndependentSampler::new(1);
+        let mut vpls = Vec::new();
+        for index in 0..n_paths {
+            sampler.start_pixel_sample((0, 0), index);
+            integrator.trace_light_path(&scene, &mut sampler, &mut vpls);
+        }
+        let hit = scene.find_first_hit(&ray).unwrap();
+        let vpl = integrator.gather(&hit, &-ray.d, &scene, &vpls).luminance();
+
+        let n = 100000;
+        let path = mean_li(&PathIntegrator::new(2, 10), &scene, &ray, n)
+            - mean_li(&PathIntegrator::new(1, 10), &scene, &ray, n);
+        assert!(path > 0.0);
+        assert!((vpl - path).abs() < 0.05 * path, "{vpl} {path}");
+    }
+}
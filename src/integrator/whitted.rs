@@ -0,0 +1,103 @@
+use crate::{
+    common::sampler::Sampler,
+    integrator::Integrator,
+    render::{colour::Colour, ray::Ray},
+    scene::Scene,
+};
+
+/// Classic recursive ray tracer: Phong shading with hard shadows plus
+/// perfect reflection and refraction. Every light is evaluated at a single
+/// fixed sample, so area lights behave like point lights and the result is
+/// free of noise.
+pub struct WhittedIntegrator {
+    /// Maximum number of specular bounces.
+    max_depth: usize,
+    /// Ambient illumination scaled by the `ka` coefficient.
+    ambient: Colour,
+}
+
+impl WhittedIntegrator {
+    pub fn new(max_depth: usize, ambient: Colour) -> Self {
+        Self { max_depth, ambient }
+    }
+
+    fn trace(&self, ray: &Ray, scene: &Scene, depth: usize) -> Colour {
+        let Some(hit) = scene.find_first_hit(ray) else {
+            return Colour::new();
+        };
+        let material = hit.material;
+        let wo = -ray.d.normalize();
+
+        let mut l = material.emitted(&hit, &wo) + material.ambient() * self.ambient;
+        for light in scene.lights() {
+            let Some(sample) = light.sample_li(&hit.p, (0.5, 0.5)) else {
+                continue;
+            };
+            if sample.pdf == 0.0 || sample.radiance.is_black() {
+                continue;
+            }
+            let phong = material.phong(&hit, &wo, &sample.wi);
+            if phong.is_black() || !scene.unoccluded(&hit.spawn_ray_to(&sample.p)) {
+                continue;
+            }
+            l += phong * sample.radiance / sample.pdf;
+        }
+
+        if depth < self.max_depth {
+            let lobes = material.specular_lobes(&hit, &wo);
+            for (wi, weight) in lobes.reflection.into_iter().chain(lobes.transmission) {
+                if !weight.is_black() {
+                    l += weight * self.trace(&hit.spawn_ray(&wi), scene, depth + 1);
+                }
+            }
+        }
+        l
+    }
+}
+
+impl Integrator for WhittedIntegrator {
+    fn li(&self, ray: &Ray, scene: &Scene, _sampler: &mut dyn Sampler) -> Colour {
+        self.trace(ray, scene, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        common::sampler::IndependentSampler,
+        material::{Material, Phong},
+        math::{matrix4::Matrix4, transform::Transform, vec3::Vec3},
+        shape::{primitive::Primitive, sphere::Sphere},
+    };
+
+    #[test]
+    fn test_mirror_reflects_emitter_radiance() {
+        // A mirror sphere facing an emitter behind the camera, with the
+        // Phong terms switched off so only the reflection remains.
+        let mut scene = Scene::new();
+        let mirror = Material::metal(Colour::grey(1.0), 0.0).with_phong(Phong::new(0.0, 0.0, 0.0));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::new_identity(),
+            Arc::new(mirror),
+        ));
+        scene.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::from_matrix(Matrix4::new_translate([0.0, 0.0, 10.0])),
+            Arc::new(Material::light(Colour::rgb(2.0, 3.0, 4.0))),
+        ));
+
+        let integrator = WhittedIntegrator::new(1, Colour::new());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let l = integrator.li(&ray, &scene, &mut IndependentSampler::new(0));
+        assert_eq!(l, Colour::rgb(2.0, 3.0, 4.0));
+
+        // Without bounces the mirror is black.
+        let integrator = WhittedIntegrator::new(0, Colour::new());
+        let l = integrator.li(&ray, &scene, &mut IndependentSampler::new(0));
+        assert!(l.is_black());
+    }
+}
@@ -2,9 +2,11 @@
 
 pub mod accel;
 pub mod common;
+pub mod integrator;
 pub mod light;
 pub mod material;
 pub mod math;
+pub mod medium;
 pub mod render;
 pub mod scene;
 pub mod shape;
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use crate::{
+    light::{EmissionSample, Light, LightSample},
+    math::{
+        sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere},
+        transform::Transform,
+        vec3::Vec3,
+    },
+    render::{colour::Colour, ray::Ray},
+    shape::{Geometry, offset_origin},
+};
+
+/// Light emitted from the front side of a shape.
+pub struct AreaLight {
+    shape: Arc<dyn Geometry>,
+    transform: Transform,
+    emission: Colour,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn Geometry>, transform: Transform, emission: Colour) -> Self {
+        Self {
+            shape,
+            transform,
+            emission,
+        }
+    }
+
+    /// Bring a solid angle density measured in local space at `p_local`
+    /// for the surface point `s` with normal `n` into world space. Returns
+    /// the world space point, normal and density.
+    fn to_world(
+        &self,
+        p_local: &Vec3,
+        p: &Vec3,
+        s: &Vec3,
+        n: &Vec3,
+        pdf: f64,
+    ) -> Option<(Vec3, Vec3, f64)> {
+        let d_local = *s - *p_local;
+        let dist_squared_local = d_local.length_squared();
+        let cos_local = n.dot(&d_local).abs() / dist_squared_local.sqrt();
+        if dist_squared_local == 0.0 || cos_local == 0.0 {
+            return None;
+        }
+        let pdf_area = pdf * cos_local / dist_squared_local / self.transform.area_scale(n);
+
+        let s_world = self.transform.apply_point(s);
+        let n_world = self.transform.apply_normal(n).normalize();
+        let d = s_world - *p;
+        let dist_squared = d.length_squared();
+        if dist_squared == 0.0 {
+            return None;
+        }
+        let cos_theta = n_world.dot(&d).abs() / dist_squared.sqrt();
+        if cos_theta == 0.0 {
+            return None;
+        }
+        Some((s_world, n_world, pdf_area * dist_squared / cos_theta))
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, p: &Vec3, samples: (f64, f64)) -> Option<LightSample> {
+        let p_local = self.transform.apply_inv_point(p);
+        let sample = self.shape.sample(&p_local, &samples);
+        if sample.pdf == 0.0 {
+            return None;
+        }
+        let (s, n, pdf) = self.to_world(&p_local, p, &sample.p, &sample.n, sample.pdf)?;
+        let wi = (s - *p).normalize();
+        Some(LightSample {
+            wi,
+            radiance: self.l(&n, &-wi),
+            pdf,
+            p: s,
+            n,
+        })
+    }
+
+    fn sample_le(&self, u1: (f64, f64), u2: (f64, f64)) -> Option<EmissionSample> {
+        let sample = self.shape.sample_uniform(&u1);
+        let pdf_pos = sample.pdf / self.transform.area_scale(&sample.n);
+        let p = self.transform.apply_point(&sample.p);
+        let n = self.transform.apply_normal(&sample.n).normalize();
+        let local = cosine_sample_hemisphere(u2);
+        let pdf_dir = cosine_hemisphere_pdf(local.z);
+        if pdf_pos == 0.0 || pdf_dir == 0.0 {
+            return None;
+        }
+        let d = n.from_local(&local);
+        Some(EmissionSample {
+            ray: Ray::new(offset_origin(&p, &n, &d), d),
+            n,
+            le: self.l(&n, &d),
+            pdf_pos,
+            pdf_dir,
+        })
+    }
+
+    fn pdf_le(&self, ray: &Ray, n: &Vec3) -> (f64, f64) {
+        let n_local = self.transform.apply_inv_normal(n);
+        let p_local = self.transform.apply_inv_point(&ray.p);
+        let pdf_pos = self.shape.pdf_area(&p_local) / self.transform.area_scale(&n_local);
+        let pdf_dir = cosine_hemisphere_pdf(n.dot(&ray.d.normalize_or_zero()));
+        (pdf_pos, pdf_dir)
+    }
+
+    fn pdf_li(&self, p: &Vec3, wi: &Vec3) -> f64 {
+        let ray = Ray::new(*p, *wi).apply_inv(&self.transform);
+        let Some(hit) = self.shape.intersect_local(&ray) else {
+            return 0.0;
+        };
+        let pdf = self.shape.pdf(&ray.p, &ray.d);
+        if pdf == 0.0 {
+            return 0.0;
+        }
+        self.to_world(&ray.p, p, &hit.p, &hit.n, pdf)
+            .map_or(0.0, |(_, _, pdf)| pdf)
+    }
+
+    fn l(&self, n: &Vec3, w: &Vec3) -> Colour {
+        if n.dot(w) > 0.0 {
+            self.emission
+        } else {
+            Colour::new()
+        }
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::matrix4::Matrix4, shape::sphere::Sphere};
+
+    #[test]
+    fn test_sample_pdf_matches_pdf_li() {
+        let mut mat = Matrix4::new_translate([0.0, 0.0, 3.0]);
+        mat.scale(2.0, 1.0, 0.5);
+        let light = AreaLight::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::from_matrix(mat),
+            Colour::grey(1.0),
+        );
+        let p = Vec3::new(0.3, -0.2, 0.0);
+        for samples in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let sample = light.sample_li(&p, samples).unwrap();
+            let pdf = light.pdf_li(&p, &sample.wi);
+            assert!(
+                (sample.pdf - pdf).abs() < 1e-6 * pdf,
+                "{} != {}",
+                sample.pdf,
+                pdf
+            );
+        }
+    }
+}
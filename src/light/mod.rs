@@ -1,3 +1,59 @@
-pub trait Light {
-    // TODO
+pub mod area;
+pub mod point;
+
+use crate::{
+    math::vec3::Vec3,
+    render::{colour::Colour, ray::Ray},
+};
+
+/// Incident illumination sampled from a light.
+pub struct LightSample {
+    /// Unit direction from the receiving point towards the light.
+    pub wi: Vec3,
+    /// Radiance arriving along `wi`, ignoring occlusion.
+    pub radiance: Colour,
+    /// Solid angle density of `wi`, or one for delta lights.
+    pub pdf: f64,
+    /// Point on the light, used to trace the shadow ray.
+    pub p: Vec3,
+    /// Surface normal at `p`, zero for lights without a surface.
+    pub n: Vec3,
+}
+
+/// A ray of light leaving a light, as used to start light paths.
+pub struct EmissionSample {
+    pub ray: Ray,
+    /// Surface normal at the origin of the ray, zero for lights without a
+    /// surface.
+    pub n: Vec3,
+    /// Radiance carried along the ray.
+    pub le: Colour,
+    /// Area density of the origin, or one for lights at a single point.
+    pub pdf_pos: f64,
+    /// Solid angle density of the direction.
+    pub pdf_dir: f64,
+}
+
+pub trait Light: Send + Sync {
+    /// Sample a direction from `p` towards the light using the samples passed.
+    fn sample_li(&self, p: &Vec3, samples: (f64, f64)) -> Option<LightSample>;
+
+    /// Solid angle density that `sample_li` picks direction `wi` from `p`.
+    fn pdf_li(&self, p: &Vec3, wi: &Vec3) -> f64;
+
+    /// Sample a ray leaving the light, `u1` picks the origin and `u2` the
+    /// direction.
+    fn sample_le(&self, u1: (f64, f64), u2: (f64, f64)) -> Option<EmissionSample>;
+
+    /// Positional and directional densities that `sample_le` generates
+    /// `ray`, whose origin lies on the light with normal `n`.
+    fn pdf_le(&self, ray: &Ray, n: &Vec3) -> (f64, f64);
+
+    /// Radiance leaving a point of the light with normal `n` towards `w`.
+    /// Lights at a single point return their intensity.
+    fn l(&self, n: &Vec3, w: &Vec3) -> Colour;
+
+    /// Whether the light is described by a delta distribution, so it can
+    /// only be reached by sampling it explicitly.
+    fn is_delta(&self) -> bool;
 }
@@ -0,0 +1,66 @@
+use std::f64::consts::PI;
+
+use crate::{
+    light::{EmissionSample, Light, LightSample},
+    math::{sampling::uniform_sample_sphere, vec3::Vec3},
+    render::{colour::Colour, ray::Ray},
+};
+
+/// An isotropic point light.
+pub struct PointLight {
+    position: Vec3,
+    /// Radiant intensity.
+    intensity: Colour,
+}
+
+impl PointLight {
+    pub fn new(position: Vec3, intensity: Colour) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: &Vec3, _samples: (f64, f64)) -> Option<LightSample> {
+        let d = self.position - *p;
+        let dist_squared = d.length_squared();
+        if dist_squared == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            wi: d / dist_squared.sqrt(),
+            radiance: self.intensity / dist_squared,
+            pdf: 1.0,
+            p: self.position,
+            n: Vec3::zero(),
+        })
+    }
+
+    fn sample_le(&self, _u1: (f64, f64), u2: (f64, f64)) -> Option<EmissionSample> {
+        Some(EmissionSample {
+            ray: Ray::new(self.position, uniform_sample_sphere(u2)),
+            n: Vec3::zero(),
+            le: self.intensity,
+            pdf_pos: 1.0,
+            pdf_dir: 1.0 / (4.0 * PI),
+        })
+    }
+
+    fn pdf_le(&self, _ray: &Ray, _n: &Vec3) -> (f64, f64) {
+        (0.0, 1.0 / (4.0 * PI))
+    }
+
+    fn pdf_li(&self, _p: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
+
+    fn l(&self, _n: &Vec3, _w: &Vec3) -> Colour {
+        self.intensity
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
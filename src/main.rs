@@ -1,14 +1,32 @@
-use std::path::Path;
+use std::sync::Arc;
 
 use clap::Parser;
 
 use ray_tracer::{
-    common::options::Options,
-    math::vec3::Vec3,
-    render::{
-        camera::{Camera, CameraIterator},
-        image::Image,
+    common::{
+        options::{IntegratorKind, Options},
+        sampler::IndependentSampler,
+    },
+    integrator::{
+        Integrator,
+        ao::AoIntegrator,
+        bdpt::BdptIntegrator,
+        debug::DebugIntegrator,
+        direct::DirectLightingIntegrator,
+        eye_light::EyeLightIntegrator,
+        gradient::GradientPathIntegrator,
+        irradiance_cache::IrradianceCacheIntegrator,
+        light_tracing::LightTracingIntegrator,
+        path::{GuidingConfig, PathIntegrator},
+        pssmlt::PssmltIntegrator,
+        sppm::SppmIntegrator,
+        volpath::VolPathIntegrator,
+        vpl::VplIntegrator,
+        whitted::WhittedIntegrator,
     },
+    math::vec3::Vec3,
+    medium::homogeneous::HomogeneousMedium,
+    render::{camera::Camera, colour::Colour, renderer::Renderer},
     scene::Scene,
 };
 
@@ -18,15 +36,101 @@ fn main() -> std::io::Result<()> {
     let e = Vec3::new(6.0, -6.0, 1.0);
     let g = -e;
     let t = Vec3::new(0.0, 0.0, 1.0);
-    let f = 1.0;
-    let window_top = 1.0;
-    let window_left = 1.0;
-    let image_size = (1.0, 1.0);
-    let camera = Camera::new(e, g, t, f, window_top, window_left, image_size, options.resolution);
+    let mut camera = Camera::new(
+        e,
+        g,
+        t,
+        options.focal_length,
+        options.resolution,
+        options.filename.clone(),
+    );
+
+    let mut scene = Scene::new();
+    scene.dummy();
+    if options.fog > 0.0 {
+        scene.set_medium(Arc::new(HomogeneousMedium::new(
+            Colour::new(),
+            Colour::grey(options.fog),
+            0.0,
+        )));
+    }
 
-    let mut image = Image::new(options.resolution);
-    let scene = Scene::new();
-    let path = Path::new("./output.ppm");
-    image.save_to_file(path)?;
-    Ok(())
+    let integrator: Box<dyn Integrator> = match options.integrator {
+        IntegratorKind::Ao => Box::new(AoIntegrator::new(options.ao_distance, options.ao_samples)),
+        IntegratorKind::Bdpt => Box::new(BdptIntegrator::new(
+            options.recursion_depth,
+            options.bdpt_strategies,
+        )),
+        IntegratorKind::Debug => Box::new(DebugIntegrator::new(options.debug_mode)),
+        IntegratorKind::Direct => Box::new(DirectLightingIntegrator::new(
+            options.light_strategy,
+            options.recursion_depth,
+        )),
+        IntegratorKind::EyeLight => Box::new(EyeLightIntegrator),
+        IntegratorKind::Gpt => Box::new(GradientPathIntegrator::new(
+            PathIntegrator::new(options.recursion_depth, options.rr_depth),
+            options.gpt_reconstruction,
+            options.gpt_alpha,
+        )),
+        IntegratorKind::IrradianceCache => {
+            let integrator = IrradianceCacheIntegrator::new(
+                options.recursion_depth,
+                options.rr_depth,
+                options.ic_accuracy,
+                options.ic_samples,
+                options.ic_min_spacing,
+                options.ic_max_spacing,
+            );
+            match options.ic_file.clone() {
+                Some(file) => Box::new(integrator.with_file(file)),
+                None => Box::new(integrator),
+            }
+        }
+        IntegratorKind::LightTracing => Box::new(LightTracingIntegrator::new(
+            options.recursion_depth,
+            options.rr_depth,
+        )),
+        IntegratorKind::Path => {
+            let path = PathIntegrator::new(options.recursion_depth, options.rr_depth);
+            if options.guiding {
+                Box::new(path.with_guiding(GuidingConfig {
+                    iterations: options.guiding_iterations,
+                    memory_budget: options.guiding_memory << 20,
+                }))
+            } else {
+                Box::new(path)
+            }
+        }
+        IntegratorKind::Pssmlt => Box::new(PssmltIntegrator::new(
+            PathIntegrator::new(options.recursion_depth, options.rr_depth),
+            options.mlt_bootstrap,
+            options.mlt_chains,
+            options.mlt_mutations,
+            options.mlt_sigma,
+            options.mlt_large_step,
+        )),
+        IntegratorKind::Sppm => Box::new(SppmIntegrator::new(
+            options.sppm_iterations,
+            options.photons,
+            options.sppm_radius,
+            options.recursion_depth,
+        )),
+        IntegratorKind::VolPath => Box::new(VolPathIntegrator::new(
+            options.recursion_depth,
+            options.rr_depth,
+        )),
+        IntegratorKind::Vpl => Box::new(VplIntegrator::new(
+            options.recursion_depth,
+            options.rr_depth,
+            options.vpl_paths,
+            options.vpl_min_distance,
+        )),
+        IntegratorKind::Whitted => Box::new(WhittedIntegrator::new(
+            options.recursion_depth,
+            Colour::grey(1.0),
+        )),
+    };
+    let mut sampler = IndependentSampler::new(options.seed);
+    Renderer::new(options.spp).render(integrator.as_ref(), &scene, &mut camera, &mut sampler);
+    camera.film().save()
 }
@@ -1,9 +1,15 @@
-#![allow(unused_variables)]
+use std::f64::consts::PI;
 
+use crate::math::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
 use crate::math::vec3::Vec3;
 use crate::render::colour::Colour;
 use crate::shape::HitRecord;
 
+/// Roughness below which a metal is treated as a perfect mirror.
+const MIN_ROUGHNESS: f64 = 1e-3;
+
+/// Coefficients of the Phong reflection model, used by the Whitted
+/// integrator. The specular exponent is derived from the roughness.
 #[derive(Clone, Debug, Default)]
 pub struct Phong {
     ka: f64,
@@ -11,30 +17,357 @@ pub struct Phong {
     ks: f64,
 }
 
+impl Phong {
+    pub fn new(ka: f64, kd: f64, ks: f64) -> Self {
+        Self { ka, kd, ks }
+    }
+}
+
 // Material properties for surface interaction
 #[derive(Clone, Debug, Default)]
 pub struct Material {
-    albedo: Vec3,
     roughness: f64,
     metallic: f64,
     phong: Phong,
     colour: Colour,
+    emission: Colour,
+    /// Index of refraction of a clear dielectric, `None` for opaque surfaces.
+    ior: Option<f64>,
+    /// An invisible surface that only bounds a participating medium.
+    interface: bool,
+}
+
+/// Perfectly specular directions leaving a surface, with the fraction of
+/// light each of them carries.
+#[derive(Debug, Default)]
+pub struct SpecularLobes {
+    pub reflection: Option<(Vec3, Colour)>,
+    pub transmission: Option<(Vec3, Colour)>,
 }
 
 // Surface interaction result
 #[derive(Debug)]
 pub struct SurfaceInteraction {
     pub scattered_direction: Option<Vec3>,
-    // pub attenuation: Vec3,
-    // pub pdf: f64,
+    /// Throughput weight of the scattered direction, i.e. the BSDF times the
+    /// cosine term over the pdf.
+    pub attenuation: Colour,
+    /// Density the direction was sampled with. For specular directions this
+    /// is the probability of picking the specular lobe.
+    pub pdf: f64,
+    /// Whether the direction was sampled from a delta distribution.
+    pub specular: bool,
+    /// Radiance emitted by the surface towards the viewer.
     pub colour: Colour,
 }
 
 impl Material {
-    pub fn interact(&self, hit: &HitRecord) -> SurfaceInteraction {
-        let max_depth = 10;
-        let acc = Colour::new();
+    /// Opaque surface with a diffuse base and a glossy lobe weighted by
+    /// `metallic`. A zero `roughness` makes the glossy lobe a perfect mirror.
+    pub fn new(colour: Colour, roughness: f64, metallic: f64) -> Self {
+        let metallic = metallic.clamp(0.0, 1.0);
+        Self {
+            roughness,
+            metallic,
+            phong: Phong::new(0.1, 1.0 - metallic, metallic),
+            colour,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_phong(mut self, phong: Phong) -> Self {
+        self.phong = phong;
+        self
+    }
+
+    /// A Lambertian surface of the given colour.
+    pub fn diffuse(colour: Colour) -> Self {
+        Self::new(colour, 1.0, 0.0)
+    }
+
+    pub fn metal(colour: Colour, roughness: f64) -> Self {
+        Self::new(colour, roughness, 1.0)
+    }
+
+    /// A smooth clear dielectric, e.g. glass with `ior = 1.5`.
+    pub fn dielectric(ior: f64) -> Self {
+        Self {
+            colour: Colour::grey(1.0),
+            ior: Some(ior),
+            ..Default::default()
+        }
+    }
+
+    /// An invisible surface that lets light through unchanged, used to mark
+    /// the boundary of a participating medium.
+    pub fn interface() -> Self {
+        Self {
+            interface: true,
+            ..Default::default()
+        }
+    }
+
+    /// A black surface emitting `emission` from its front side.
+    pub fn light(emission: Colour) -> Self {
+        Self {
+            emission,
+            ..Default::default()
+        }
+    }
+
+    pub fn emission(&self) -> Colour {
+        self.emission
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        !self.emission.is_black()
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.interface
+    }
+
+    /// Albedo of the diffuse lobe.
+    pub fn diffuse_reflectance(&self) -> Colour {
+        if self.interface || self.ior.is_some() {
+            return Colour::new();
+        }
+        (1.0 - self.metallic) * self.colour
+    }
+
+    /// Whether the BSDF only has delta lobes, so that evaluating it for a
+    /// given pair of directions always yields zero.
+    pub fn is_delta(&self) -> bool {
+        self.interface || self.ior.is_some() || (self.metallic >= 1.0 && self.is_mirror())
+    }
+
+    fn is_mirror(&self) -> bool {
+        self.roughness < MIN_ROUGHNESS
+    }
+
+    /// Radiance emitted at the hit point towards `wo`.
+    pub fn emitted(&self, hit: &HitRecord, wo: &Vec3) -> Colour {
+        if hit.n.dot(wo) > 0.0 {
+            self.emission
+        } else {
+            Colour::new()
+        }
+    }
+
+    /// Ambient term of the Phong model.
+    pub fn ambient(&self) -> Colour {
+        self.phong.ka * self.colour
+    }
+
+    /// Diffuse and specular terms of the Phong model for light arriving from
+    /// `wi`, including the cosine factor.
+    pub fn phong(&self, hit: &HitRecord, wo: &Vec3, wi: &Vec3) -> Colour {
+        let n = face_forward(&hit.ns, wo);
+        let cos_i = n.dot(wi);
+        if cos_i <= 0.0 {
+            return Colour::new();
+        }
+        let cos_alpha = (-*wi).reflect(&n).dot(wo).max(0.0);
+        self.phong.kd * cos_i * self.colour
+            + Colour::grey(self.phong.ks * cos_alpha.powf(self.exponent()))
+    }
+
+    /// The mirror reflection and refraction directions of the delta lobes.
+    pub fn specular_lobes(&self, hit: &HitRecord, wo: &Vec3) -> SpecularLobes {
+        if self.interface {
+            return SpecularLobes {
+                reflection: None,
+                transmission: Some((-*wo, Colour::grey(1.0))),
+            };
+        }
+        if let Some(ior) = self.ior {
+            let entering = hit.n.dot(wo) > 0.0;
+            let n = if entering { hit.ns } else { -hit.ns };
+            let eta = if entering { 1.0 / ior } else { ior };
+            let f = fresnel_dielectric(n.dot(wo), eta);
+            return SpecularLobes {
+                reflection: Some(((-*wo).reflect(&n), f * self.colour)),
+                transmission: (-*wo)
+                    .refract(&n, eta)
+                    .map(|wt| (wt, (1.0 - f) * self.colour)),
+            };
+        }
+        if self.is_mirror() && self.metallic > 0.0 {
+            let n = face_forward(&hit.ns, wo);
+            return SpecularLobes {
+                reflection: Some(((-*wo).reflect(&n), self.metallic * self.colour)),
+                transmission: None,
+            };
+        }
+        SpecularLobes::default()
+    }
+
+    /// Evaluate the non-delta part of the BSDF for light arriving from `wi`
+    /// and leaving towards `wo`.
+    pub fn eval(&self, hit: &HitRecord, wo: &Vec3, wi: &Vec3) -> Colour {
+        if self.interface || self.ior.is_some() {
+            return Colour::new();
+        }
+        let n = face_forward(&hit.ns, wo);
+        let cos_i = n.dot(wi);
+        if cos_i <= 0.0 {
+            return Colour::new();
+        }
+        let mut f = (1.0 - self.metallic) * self.colour / PI;
+        if !self.is_mirror() {
+            f += self.metallic * self.colour * self.glossy_lobe(&n, wo, wi).0;
+        }
+        f
+    }
+
+    /// The density `interact` samples `wi` with, given `wo`, restricted to
+    /// the non-delta lobes.
+    pub fn pdf(&self, hit: &HitRecord, wo: &Vec3, wi: &Vec3) -> f64 {
+        if self.interface || self.ior.is_some() {
+            return 0.0;
+        }
+        let n = face_forward(&hit.ns, wo);
+        let cos_i = n.dot(wi);
+        if cos_i <= 0.0 {
+            return 0.0;
+        }
+        let mut pdf = (1.0 - self.metallic) * cosine_hemisphere_pdf(cos_i);
+        if !self.is_mirror() {
+            pdf += self.metallic * self.glossy_lobe(&n, wo, wi).1;
+        }
+        pdf
+    }
+
+    /// Scatter the light arriving from `wo` (pointing away from the surface)
+    /// using the samples passed. `u` picks a lobe, `samples` a direction in it.
+    pub fn interact(
+        &self,
+        hit: &HitRecord,
+        wo: &Vec3,
+        u: f64,
+        samples: (f64, f64),
+    ) -> SurfaceInteraction {
+        let colour = self.emitted(hit, wo);
+        if self.interface {
+            return SurfaceInteraction {
+                scattered_direction: Some(-*wo),
+                attenuation: Colour::grey(1.0),
+                pdf: 1.0,
+                specular: true,
+                colour,
+            };
+        }
+        if let Some(ior) = self.ior {
+            return self.interact_dielectric(hit, wo, ior, u, colour);
+        }
+
+        let n = face_forward(&hit.ns, wo);
+        let wi = if u < self.metallic {
+            let r = (-*wo).reflect(&n);
+            if self.is_mirror() {
+                return SurfaceInteraction {
+                    scattered_direction: Some(r),
+                    attenuation: self.colour,
+                    pdf: self.metallic,
+                    specular: true,
+                    colour,
+                };
+            }
+            r.from_local(&self.sample_glossy_lobe(samples))
+        } else {
+            n.from_local(&cosine_sample_hemisphere(samples))
+        };
+
+        let pdf = self.pdf(hit, wo, &wi);
+        if pdf == 0.0 {
+            return SurfaceInteraction {
+                scattered_direction: None,
+                attenuation: Colour::new(),
+                pdf,
+                specular: false,
+                colour,
+            };
+        }
+        SurfaceInteraction {
+            scattered_direction: Some(wi),
+            attenuation: self.eval(hit, wo, &wi) * n.dot(&wi).abs() / pdf,
+            pdf,
+            specular: false,
+            colour,
+        }
+    }
+
+    fn interact_dielectric(
+        &self,
+        hit: &HitRecord,
+        wo: &Vec3,
+        ior: f64,
+        u: f64,
+        colour: Colour,
+    ) -> SurfaceInteraction {
+        let entering = hit.n.dot(wo) > 0.0;
+        let n = if entering { hit.ns } else { -hit.ns };
+        let eta = if entering { 1.0 / ior } else { ior };
+        let cos_i = n.dot(wo);
+        let f = fresnel_dielectric(cos_i, eta);
+
+        let (wi, pdf) = match (-*wo).refract(&n, eta) {
+            Some(wt) if u >= f => (wt, 1.0 - f),
+            _ => ((-*wo).reflect(&n), f.max(f64::MIN_POSITIVE)),
+        };
+        SurfaceInteraction {
+            scattered_direction: Some(wi),
+            attenuation: self.colour,
+            pdf,
+            specular: true,
+            colour,
+        }
+    }
+
+    /// Phong exponent equivalent to the roughness.
+    fn exponent(&self) -> f64 {
+        let alpha = self.roughness.clamp(MIN_ROUGHNESS, 1.0);
+        2.0 / (alpha * alpha) - 2.0
+    }
+
+    /// Energy normalized Phong lobe around the mirror direction, returns the
+    /// BSDF value (without colour) and the pdf of sampling it.
+    fn glossy_lobe(&self, n: &Vec3, wo: &Vec3, wi: &Vec3) -> (f64, f64) {
+        let r = (-*wo).reflect(n);
+        let cos_alpha = r.dot(wi);
+        if cos_alpha <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let e = self.exponent();
+        let lobe = cos_alpha.powf(e);
+        ((e + 2.0) / (2.0 * PI) * lobe, (e + 1.0) / (2.0 * PI) * lobe)
+    }
+
+    /// Direction around `+z` distributed proportionally to `cos^e`.
+    fn sample_glossy_lobe(&self, samples: (f64, f64)) -> Vec3 {
+        let cos_alpha = (1.0 - samples.0).powf(1.0 / (self.exponent() + 1.0));
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+        let phi = 2.0 * PI * samples.1;
+        Vec3::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha)
+    }
+}
+
+/// Flip `n` onto the same side of the surface as `w`.
+fn face_forward(n: &Vec3, w: &Vec3) -> Vec3 {
+    if n.dot(w) < 0.0 { -*n } else { *n }
+}
 
-        todo!()
+/// Fresnel reflectance of a dielectric interface, where `cos_i` is measured
+/// on the incident side and `eta` is the ratio of the incident index over
+/// the transmitted index.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(-1.0, 1.0);
+    let sin_t_squared = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin_t_squared >= 1.0 {
+        return 1.0;
     }
+    let cos_t = (1.0 - sin_t_squared).sqrt();
+    let r_parallel = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let r_perpendicular = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
 }
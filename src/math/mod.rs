@@ -1,4 +1,6 @@
 pub mod matrix4;
+pub mod roots;
+pub mod sampling;
 pub mod transform;
 pub mod vec3;
 pub mod vec4;
@@ -0,0 +1,175 @@
+/// Real roots `t0 <= t1` of `a t^2 + b t + c`, computed without the
+/// cancellation of the textbook formula. A linear equation gives its single
+/// root twice.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0.0 {
+        if b == 0.0 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return Some((0.0, 0.0));
+    }
+    let (t0, t1) = (q / a, c / q);
+    Some((t0.min(t1), t0.max(t1)))
+}
+
+/// Newton iterations polishing the roots of cubics and quartics.
+const POLISH_ITERATIONS: usize = 8;
+
+/// Improve the root `t` of the polynomial with the coefficients `coeffs`,
+/// highest degree first, by Newton iterations. Steps that do not bring the
+/// polynomial closer to zero are rejected, so the closed form roots can only
+/// get better.
+fn polish_root(coeffs: &[f64], mut t: f64) -> f64 {
+    let eval = |t: f64| {
+        coeffs
+            .iter()
+            .fold((0.0, 0.0), |(f, df), &c| (f * t + c, df * t + f))
+    };
+    let (mut f, mut df) = eval(t);
+    for _ in 0..POLISH_ITERATIONS {
+        if f == 0.0 || df == 0.0 {
+            break;
+        }
+        let next = t - f / df;
+        let (f_next, df_next) = eval(next);
+        if f_next.is_nan() || f_next.abs() >= f.abs() {
+            break;
+        }
+        (t, f, df) = (next, f_next, df_next);
+    }
+    t
+}
+
+/// Real roots of `a t^3 + b t^2 + c t + d` in increasing order.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_quadratic(b, c, d).map_or_else(Vec::new, |(t0, t1)| {
+            if t0 == t1 { vec![t0] } else { vec![t0, t1] }
+        });
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    // Depressed cubic x^3 + p x + q with t = x - b / 3.
+    let p = c - b * b / 3.0;
+    let q = 2.0 * b * b * b / 27.0 - b * c / 3.0 + d;
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+
+    let mut roots = if p == 0.0 {
+        vec![(-q).cbrt()]
+    } else if discriminant > 0.0 {
+        // A single real root, from Cardano's formula arranged to avoid
+        // cancellation.
+        let u = -q.signum() * (q.abs() / 2.0 + discriminant.sqrt()).cbrt();
+        vec![u - p / (3.0 * u)]
+    } else {
+        // Three real roots, from the trigonometric form.
+        let m = 2.0 * (-p / 3.0).sqrt();
+        let theta = (3.0 * q / (p * m)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3)
+            .map(|k| m * (theta - 2.0 * std::f64::consts::PI * k as f64 / 3.0).cos())
+            .collect()
+    };
+    for root in &mut roots {
+        *root = polish_root(&[1.0, b, c, d], *root - b / 3.0);
+    }
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+/// Real roots of `a t^4 + b t^3 + c t^2 + d t + e` in increasing order, by
+/// Ferrari's method. The closed form roots lose precision when the quartic
+/// is badly conditioned, so they are polished by Newton iterations on the
+/// original polynomial.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // Depressed quartic y^4 + p y^2 + q y + r with t = y - b / 4.
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+
+    let mut ys = Vec::with_capacity(4);
+    let mut push_quadratic = |a: f64, b: f64, c: f64| {
+        if let Some((y0, y1)) = solve_quadratic(a, b, c) {
+            ys.extend([y0, y1]);
+        }
+    };
+    let scale = 1.0 + p.abs() + r.abs().sqrt();
+    if q.abs() <= 1e-12 * scale * scale.sqrt() {
+        // Biquadratic: a quadratic in y^2.
+        if let Some((z0, z1)) = solve_quadratic(1.0, p, r) {
+            for z in [z0, z1].into_iter().filter(|&z| z >= 0.0) {
+                push_quadratic(1.0, 0.0, -z);
+            }
+        }
+    } else {
+        // Any positive root m of the resolvent cubic splits the quartic into
+        // (y^2 + p / 2 + m)^2 = 2 m (y - q / (4 m))^2.
+        let Some(m) = solve_cubic(1.0, p, p * p / 4.0 - r, -q * q / 8.0)
+            .last()
+            .copied()
+        else {
+            return Vec::new();
+        };
+        if m <= 0.0 {
+            return Vec::new();
+        }
+        let s = (2.0 * m).sqrt();
+        push_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s));
+        push_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s));
+    }
+
+    let mut roots: Vec<f64> = ys
+        .into_iter()
+        .map(|y| polish_root(&[1.0, b, c, d, e], y - b / 4.0))
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quartic_roots() {
+        // (t - 1) (t - 2) (t - 3) (t - 4)
+        let roots = solve_quartic(2.0, -20.0, 70.0, -100.0, 48.0);
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < 1e-12, "{roots:?}");
+        }
+        // (t^2 + 1) (t - 5) (t + 0.5) has two real roots.
+        let roots = solve_quartic(1.0, -4.5, -1.5, -4.5, -2.5);
+        assert_eq!(roots.len(), 2);
+        assert!(
+            (roots[0] + 0.5).abs() < 1e-12 && (roots[1] - 5.0).abs() < 1e-12,
+            "{roots:?}"
+        );
+        // Biquadratic (t^2 - 4) (t^2 - 9), and no real roots at all.
+        assert_eq!(
+            solve_quartic(1.0, 0.0, -13.0, 0.0, 36.0),
+            vec![-3.0, -2.0, 2.0, 3.0]
+        );
+        assert!(solve_quartic(1.0, 0.0, 2.0, 0.0, 1.0).is_empty());
+        // Badly conditioned: roots 1000 and 1000.001 next to small ones.
+        let roots = solve_quartic(1.0, -2001.002, 1002003.002001, -1001003.001001, 1000.001);
+        for (root, expected) in roots.iter().zip([0.001, 1.0, 1000.0, 1000.001]) {
+            assert!(
+                (root - expected).abs() < 1e-6 * expected.max(1.0),
+                "{roots:?}"
+            );
+        }
+    }
+}
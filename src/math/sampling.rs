@@ -0,0 +1,43 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use super::vec3::Vec3;
+
+/// Map `u` in `[0, 1)^2` onto the unit disk with Shirley's concentric mapping.
+pub fn concentric_sample_disk(u: (f64, f64)) -> (f64, f64) {
+    let (ox, oy) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if ox == 0.0 && oy == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, FRAC_PI_4 * (oy / ox))
+    } else {
+        (oy, FRAC_PI_2 - FRAC_PI_4 * (ox / oy))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Cosine weighted direction around `+z`, with pdf `cos(theta) / pi`.
+pub fn cosine_sample_hemisphere(u: (f64, f64)) -> Vec3 {
+    let (x, y) = concentric_sample_disk(u);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    Vec3::new(x, y, z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+    cos_theta.max(0.0) / PI
+}
+
+/// Uniformly distributed direction on the unit sphere, with pdf `1 / (4 pi)`.
+pub fn uniform_sample_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Uniformly distributed barycentric coordinates `(b0, b1)` of a point on a
+/// triangle, the third one being `1 - b0 - b1`.
+pub fn uniform_sample_triangle(u: (f64, f64)) -> (f64, f64) {
+    let su0 = u.0.sqrt();
+    (1.0 - su0, u.1 * su0)
+}
@@ -1,8 +1,10 @@
+use crate::accel::aabb::AABB;
+
 use super::{matrix4::Matrix4, vec3::Vec3};
 
 /// Transfomation used to convert the coordinate between camera and
 /// world coordinate
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct Transform {
     /// Camera to world matrix
     pub mat: Matrix4,
@@ -15,9 +17,9 @@ impl Transform {
     pub fn new(e: Vec3, u: Vec3, v: Vec3, w: Vec3) -> Self {
         // We want an orthongonal matrix M = [u v w] so we can easily compute
         // its inverse by M^T.
-        u.normalize();
-        v.normalize();
-        w.normalize();
+        let u = u.normalize();
+        let v = v.normalize();
+        let w = w.normalize();
         let mat = Matrix4::new(
             [u[0], v[0], w[0], e[0],
              u[1], v[1], w[1], e[1],
@@ -25,12 +27,17 @@ impl Transform {
              0.0,  0.0,  0.0,  1.0]
         );
         let inv = Matrix4::new(
-            [u[0],       u[1],       u[2],       0.0,
-             v[0],       v[1],       v[2],       0.0,
-             w[0],       w[1],       w[2],       0.0,
-             -u.dot(&e), -v.dot(&e), -w.dot(&e), 1.0]
+            [u[0], u[1], u[2], -u.dot(&e),
+             v[0], v[1], v[2], -v.dot(&e),
+             w[0], w[1], w[2], -w.dot(&e),
+             0.0,  0.0,  0.0,  1.0]
+        );
+        debug_assert!(
+            (mat * inv - Matrix4::new_identity())
+                .data()
+                .iter()
+                .all(|x| x.abs() < 1e-9)
         );
-        assert!(mat * inv == Matrix4::new_identity());
         Transform {
             mat,
             inv,
@@ -43,4 +50,95 @@ impl Transform {
             inv: Matrix4::new_identity(),
         }
     }
+
+    /// Build a transformation from an arbitrary invertible matrix.
+    pub fn from_matrix(mat: Matrix4) -> Self {
+        Self {
+            mat,
+            inv: mat.inv(),
+        }
+    }
+
+    /// Transform a point from local to world coordinate.
+    pub fn apply_point(&self, p: &Vec3) -> Vec3 {
+        (self.mat * p.to_homo()).to_inhomo()
+    }
+
+    /// Transform a direction from local to world coordinate, ignoring the
+    /// translation.
+    pub fn apply_vector(&self, d: &Vec3) -> Vec3 {
+        mul_vector(&self.mat, d)
+    }
+
+    /// Transform a surface normal from local to world coordinate. Normals
+    /// transform by the inverse transpose so they stay perpendicular to the
+    /// surface under non-uniform scaling.
+    pub fn apply_normal(&self, n: &Vec3) -> Vec3 {
+        mul_vector_transposed(&self.inv, n)
+    }
+
+    /// Transform a point from world to local coordinate.
+    pub fn apply_inv_point(&self, p: &Vec3) -> Vec3 {
+        (self.inv * p.to_homo()).to_inhomo()
+    }
+
+    /// Transform a direction from world to local coordinate.
+    pub fn apply_inv_vector(&self, d: &Vec3) -> Vec3 {
+        mul_vector(&self.inv, d)
+    }
+
+    /// Transform a surface normal from world to local coordinate.
+    pub fn apply_inv_normal(&self, n: &Vec3) -> Vec3 {
+        mul_vector_transposed(&self.mat, n)
+    }
+
+    /// Bounding box of the transformed corners of `bounds`.
+    pub fn apply_bounds(&self, bounds: &AABB) -> AABB {
+        (0..8).fold(AABB::empty(), |acc, i| {
+            let corner = Vec3::new(
+                if i & 1 == 0 {
+                    bounds.min.x
+                } else {
+                    bounds.max.x
+                },
+                if i & 2 == 0 {
+                    bounds.min.y
+                } else {
+                    bounds.max.y
+                },
+                if i & 4 == 0 {
+                    bounds.min.z
+                } else {
+                    bounds.max.z
+                },
+            );
+            acc.union_point(&self.apply_point(&corner))
+        })
+    }
+
+    /// Ratio between a world space and a local space area element around a
+    /// surface point with local normal `n`.
+    pub fn area_scale(&self, n: &Vec3) -> f64 {
+        let m = &self.mat;
+        let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        det.abs() * self.apply_normal(&n.normalize()).length()
+    }
+}
+
+fn mul_vector(m: &Matrix4, d: &Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * d.x + m[0][1] * d.y + m[0][2] * d.z,
+        m[1][0] * d.x + m[1][1] * d.y + m[1][2] * d.z,
+        m[2][0] * d.x + m[2][1] * d.y + m[2][2] * d.z,
+    )
+}
+
+fn mul_vector_transposed(m: &Matrix4, d: &Vec3) -> Vec3 {
+    Vec3::new(
+        m[0][0] * d.x + m[1][0] * d.y + m[2][0] * d.z,
+        m[0][1] * d.x + m[1][1] * d.y + m[2][1] * d.z,
+        m[0][2] * d.x + m[1][2] * d.y + m[2][2] * d.z,
+    )
 }
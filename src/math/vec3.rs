@@ -63,6 +63,45 @@ impl Vec3 {
         }
     }
 
+    /// Mirror `self` about the normal `n`.
+    pub fn reflect(&self, n: &Vec3) -> Vec3 {
+        *self - 2.0 * self.dot(n) * *n
+    }
+
+    /// Refract the unit incident direction `self` through a surface with unit
+    /// normal `n` on the incident side, where `eta` is the ratio of the
+    /// incident index over the transmitted index. Returns `None` on total
+    /// internal reflection.
+    pub fn refract(&self, n: &Vec3, eta: f64) -> Option<Vec3> {
+        let cos_i = -self.dot(n);
+        let sin_t_squared = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+        if sin_t_squared > 1.0 {
+            return None;
+        }
+        let cos_t = (1.0 - sin_t_squared).sqrt();
+        Some(eta * *self + (eta * cos_i - cos_t) * *n)
+    }
+
+    /// Build two unit vectors that form an orthonormal basis with `self`,
+    /// which must be normalized.
+    pub fn coordinate_system(&self) -> (Vec3, Vec3) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+        (
+            Vec3::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Vec3::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
+
+    /// Express the local direction `v` (with `self` as the z axis) in the
+    /// frame `self` belongs to.
+    pub fn from_local(&self, v: &Vec3) -> Vec3 {
+        let (s, t) = self.coordinate_system();
+        v.x * s + v.y * t + v.z * *self
+    }
+
     pub fn to_homo(&self) -> Vec4 {
         Vec4::new(self.x, self.y, self.z, 1.0)
     }
@@ -0,0 +1,206 @@
+use crate::{
+    accel::aabb::AABB,
+    common::sampler::Sampler,
+    math::vec3::Vec3,
+    medium::{Medium, MediumSample, phase::HenyeyGreenstein},
+    render::{colour::Colour, ray::Ray},
+};
+
+/// A heterogeneous medium whose density is given on a voxel grid spanning an
+/// axis aligned box, e.g. smoke. The coefficients are scaled by the density
+/// interpolated trilinearly between voxel centres.
+///
+/// Distances are sampled with delta tracking and transmittance is estimated
+/// with ratio tracking, both against the maximum density of the grid.
+pub struct GridMedium {
+    bounds: AABB,
+    resolution: (usize, usize, usize),
+    density: Vec<f64>,
+    max_density: f64,
+    /// Extinction coefficient at unit density.
+    sigma_t: f64,
+    /// Single scattering albedo, `sigma_s / sigma_t`.
+    albedo: Colour,
+    phase: HenyeyGreenstein,
+}
+
+impl GridMedium {
+    /// `density` holds `resolution.0 * resolution.1 * resolution.2` values,
+    /// with `x` varying fastest.
+    pub fn new(
+        bounds: AABB,
+        resolution: (usize, usize, usize),
+        density: Vec<f64>,
+        sigma_t: f64,
+        albedo: Colour,
+        g: f64,
+    ) -> Self {
+        assert_eq!(density.len(), resolution.0 * resolution.1 * resolution.2);
+        let max_density = density.iter().copied().fold(0.0, f64::max);
+        Self {
+            bounds,
+            resolution,
+            density,
+            max_density,
+            sigma_t,
+            albedo,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+
+    /// A grid filled by evaluating `f` at the centre of every voxel.
+    pub fn from_fn(
+        bounds: AABB,
+        resolution: (usize, usize, usize),
+        f: impl Fn(&Vec3) -> f64,
+        sigma_t: f64,
+        albedo: Colour,
+        g: f64,
+    ) -> Self {
+        let (nx, ny, nz) = resolution;
+        let diagonal = bounds.diagonal();
+        let mut density = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let p = bounds.min
+                        + Vec3::new(
+                            (x as f64 + 0.5) / nx as f64 * diagonal.x,
+                            (y as f64 + 0.5) / ny as f64 * diagonal.y,
+                            (z as f64 + 0.5) / nz as f64 * diagonal.z,
+                        );
+                    density.push(f(&p).max(0.0));
+                }
+            }
+        }
+        Self::new(bounds, resolution, density, sigma_t, albedo, g)
+    }
+
+    fn voxel(&self, x: isize, y: isize, z: isize) -> f64 {
+        let (nx, ny, nz) = self.resolution;
+        let x = x.clamp(0, nx as isize - 1) as usize;
+        let y = y.clamp(0, ny as isize - 1) as usize;
+        let z = z.clamp(0, nz as isize - 1) as usize;
+        self.density[(z * ny + y) * nx + x]
+    }
+
+    /// Density at the world space point `p`.
+    fn density(&self, p: &Vec3) -> f64 {
+        if !self.bounds.contains(p) {
+            return 0.0;
+        }
+        let o = self.bounds.offset(p);
+        let (nx, ny, nz) = self.resolution;
+        let g = Vec3::new(
+            o.x * nx as f64 - 0.5,
+            o.y * ny as f64 - 0.5,
+            o.z * nz as f64 - 0.5,
+        );
+        let (x, y, z) = (g.x.floor(), g.y.floor(), g.z.floor());
+        let (dx, dy, dz) = (g.x - x, g.y - y, g.z - z);
+        let (x, y, z) = (x as isize, y as isize, z as isize);
+        let lerp = |t: f64, a: f64, b: f64| (1.0 - t) * a + t * b;
+        let d00 = lerp(dx, self.voxel(x, y, z), self.voxel(x + 1, y, z));
+        let d10 = lerp(dx, self.voxel(x, y + 1, z), self.voxel(x + 1, y + 1, z));
+        let d01 = lerp(dx, self.voxel(x, y, z + 1), self.voxel(x + 1, y, z + 1));
+        let d11 = lerp(
+            dx,
+            self.voxel(x, y + 1, z + 1),
+            self.voxel(x + 1, y + 1, z + 1),
+        );
+        lerp(dz, lerp(dy, d00, d10), lerp(dy, d01, d11))
+    }
+
+    /// Majorant extinction per unit of the ray parameter, and the range of
+    /// the ray inside the grid.
+    fn majorant(&self, ray: &Ray, t_max: f64) -> Option<(f64, (f64, f64))> {
+        let range = self.bounds.intersect(ray, t_max)?;
+        let sigma_bar = self.sigma_t * self.max_density * ray.d.length();
+        (sigma_bar > 0.0).then_some((sigma_bar, range))
+    }
+}
+
+impl Medium for GridMedium {
+    fn tr(&self, ray: &Ray, t_max: f64, sampler: &mut dyn Sampler) -> Colour {
+        let Some((sigma_bar, (t_min, t_max))) = self.majorant(ray, t_max) else {
+            return Colour::grey(1.0);
+        };
+        let mut tr = 1.0;
+        let mut t = t_min;
+        loop {
+            t -= (1.0 - sampler.get_1d()).ln() / sigma_bar;
+            if t >= t_max {
+                break;
+            }
+            tr *= 1.0 - self.density(&ray.at(&t)) / self.max_density;
+
+            // Russian roulette on paths that barely transmit.
+            if tr < 0.1 {
+                let q = 1.0 - tr;
+                if sampler.get_1d() < q {
+                    return Colour::new();
+                }
+                tr /= 1.0 - q;
+            }
+        }
+        Colour::grey(tr)
+    }
+
+    fn sample(&self, ray: &Ray, t_max: f64, sampler: &mut dyn Sampler) -> MediumSample {
+        let Some((sigma_bar, (t_min, t_max))) = self.majorant(ray, t_max) else {
+            return MediumSample {
+                t: None,
+                weight: Colour::grey(1.0),
+            };
+        };
+        let mut t = t_min;
+        loop {
+            t -= (1.0 - sampler.get_1d()).ln() / sigma_bar;
+            if t >= t_max {
+                return MediumSample {
+                    t: None,
+                    weight: Colour::grey(1.0),
+                };
+            }
+            // Real collision with the probability of the local density,
+            // otherwise a null collision that continues the walk.
+            if self.density(&ray.at(&t)) / self.max_density > sampler.get_1d() {
+                return MediumSample {
+                    t: Some(t),
+                    weight: self.albedo,
+                };
+            }
+        }
+    }
+
+    fn phase(&self) -> &HenyeyGreenstein {
+        &self.phase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::sampler::IndependentSampler, medium::homogeneous::HomogeneousMedium};
+
+    #[test]
+    fn test_constant_grid_transmittance_matches_homogeneous() {
+        let bounds = AABB::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let grid = GridMedium::from_fn(bounds, (4, 4, 4), |_| 0.5, 2.0, Colour::grey(0.8), 0.0);
+        let homogeneous = HomogeneousMedium::new(Colour::grey(1.0), Colour::new(), 0.0);
+
+        // A ray with a non-unit direction that stays inside the grid.
+        let ray = Ray::new(Vec3::new(-0.9, -0.5, 0.0), Vec3::new(2.0, 1.0, 0.0));
+        let t_max = 0.8;
+        let mut sampler = IndependentSampler::new(5);
+        let expected = homogeneous.tr(&ray, t_max, &mut sampler).r;
+        let n = 100000;
+        let mut tr = 0.0;
+        for index in 0..n {
+            sampler.start_pixel_sample((0, 0), index);
+            tr += grid.tr(&ray, t_max, &mut sampler).r;
+        }
+        let tr = tr / n as f64;
+        assert!((tr - expected).abs() < 0.01, "{tr} {expected}");
+    }
+}
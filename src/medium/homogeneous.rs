@@ -0,0 +1,74 @@
+use crate::{
+    common::sampler::Sampler,
+    medium::{Medium, MediumSample, phase::HenyeyGreenstein},
+    render::{colour::Colour, ray::Ray},
+};
+
+/// A medium with the same coefficients everywhere, e.g. fog or murky water.
+pub struct HomogeneousMedium {
+    sigma_s: Colour,
+    sigma_t: Colour,
+    phase: HenyeyGreenstein,
+}
+
+impl HomogeneousMedium {
+    /// A medium with absorption `sigma_a` and scattering `sigma_s`
+    /// coefficients per unit distance and Henyey-Greenstein asymmetry `g`.
+    pub fn new(sigma_a: Colour, sigma_s: Colour, g: f64) -> Self {
+        Self {
+            sigma_s,
+            sigma_t: sigma_a + sigma_s,
+            phase: HenyeyGreenstein::new(g),
+        }
+    }
+}
+
+impl Medium for HomogeneousMedium {
+    fn tr(&self, ray: &Ray, t_max: f64, _sampler: &mut dyn Sampler) -> Colour {
+        beer_lambert(&self.sigma_t, t_max * ray.d.length())
+    }
+
+    fn sample(&self, ray: &Ray, t_max: f64, sampler: &mut dyn Sampler) -> MediumSample {
+        // Sample the distance with the coefficient of one channel picked at
+        // random, and weight with the density averaged over all channels.
+        let channel = ((sampler.get_1d() * 3.0) as usize).min(2);
+        let length = ray.d.length();
+        let distance = -(1.0 - sampler.get_1d()).ln() / self.sigma_t[channel];
+        let t = (distance / length).min(t_max);
+        let scattered = t < t_max;
+
+        let tr = beer_lambert(&self.sigma_t, t * length);
+        let density = if scattered { self.sigma_t * tr } else { tr };
+        let pdf = density.average();
+        if pdf == 0.0 {
+            return MediumSample {
+                t: None,
+                weight: Colour::new(),
+            };
+        }
+        MediumSample {
+            t: scattered.then_some(t),
+            weight: if scattered {
+                tr * self.sigma_s / pdf
+            } else {
+                tr / pdf
+            },
+        }
+    }
+
+    fn phase(&self) -> &HenyeyGreenstein {
+        &self.phase
+    }
+}
+
+/// Transmittance over `distance`, which may be infinite.
+fn beer_lambert(sigma_t: &Colour, distance: f64) -> Colour {
+    let tr = |sigma: f64| {
+        if sigma == 0.0 {
+            1.0
+        } else {
+            (-sigma * distance).exp()
+        }
+    };
+    Colour::rgb(tr(sigma_t.r), tr(sigma_t.g), tr(sigma_t.b))
+}
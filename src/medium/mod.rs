@@ -0,0 +1,60 @@
+pub mod grid;
+pub mod homogeneous;
+pub mod phase;
+
+use std::sync::Arc;
+
+use crate::{
+    common::sampler::Sampler,
+    math::vec3::Vec3,
+    medium::phase::HenyeyGreenstein,
+    render::{colour::Colour, ray::Ray},
+};
+
+/// Outcome of sampling a free-flight distance along a ray.
+pub struct MediumSample {
+    /// Parametric distance of the scattering event along the ray, `None`
+    /// when the ray passed through the medium up to `t_max`.
+    pub t: Option<f64>,
+    /// Factor to multiply the path throughput with, i.e. the transmittance
+    /// (times the scattering coefficient at a scattering event) over the
+    /// sampling density.
+    pub weight: Colour,
+}
+
+/// A participating medium that absorbs and scatters light between surfaces.
+pub trait Medium: Send + Sync {
+    /// Transmittance along `ray` between `t = 0` and `t_max`.
+    fn tr(&self, ray: &Ray, t_max: f64, sampler: &mut dyn Sampler) -> Colour;
+
+    /// Sample the distance along `ray` at which the light is scattered,
+    /// before `t_max`.
+    fn sample(&self, ray: &Ray, t_max: f64, sampler: &mut dyn Sampler) -> MediumSample;
+
+    fn phase(&self) -> &HenyeyGreenstein;
+}
+
+/// The media on either side of a surface. A side without a medium is vacuum.
+#[derive(Clone, Default)]
+pub struct MediumInterface {
+    /// Medium on the side the normal points away from.
+    pub inside: Option<Arc<dyn Medium>>,
+    /// Medium on the side the normal points to.
+    pub outside: Option<Arc<dyn Medium>>,
+}
+
+impl MediumInterface {
+    pub fn new(inside: Option<Arc<dyn Medium>>, outside: Option<Arc<dyn Medium>>) -> Self {
+        Self { inside, outside }
+    }
+
+    /// The medium a ray leaving the surface in direction `d` travels through,
+    /// given the surface normal `n`.
+    pub fn medium(&self, n: &Vec3, d: &Vec3) -> Option<Arc<dyn Medium>> {
+        if n.dot(d) < 0.0 {
+            self.inside.clone()
+        } else {
+            self.outside.clone()
+        }
+    }
+}
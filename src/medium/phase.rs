@@ -0,0 +1,89 @@
+use std::f64::consts::PI;
+
+use crate::math::vec3::Vec3;
+
+/// Henyey-Greenstein phase function. `g` in `(-1, 1)` ranges from back
+/// scattering through isotropic (`g = 0`) to forward scattering.
+#[derive(Debug, Clone, Copy)]
+pub struct HenyeyGreenstein {
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(g: f64) -> Self {
+        Self {
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    /// Density of scattering light travelling along `-wo` into `wi`. Both
+    /// directions point away from the scattering point.
+    pub fn p(&self, wo: &Vec3, wi: &Vec3) -> f64 {
+        self.hg(-wo.dot(wi))
+    }
+
+    /// Sample the direction light arriving from `wi` came from, given the
+    /// outgoing direction `wo`. The phase function is its own pdf.
+    pub fn sample_p(&self, wo: &Vec3, samples: (f64, f64)) -> (Vec3, f64) {
+        let g = self.g;
+        // Cosine of the angle to the direction of propagation `-wo`.
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * samples.0
+        } else {
+            let sqr_term = (1.0 - g * g) / (1.0 - g + 2.0 * g * samples.0);
+            (1.0 + g * g - sqr_term * sqr_term) / (2.0 * g)
+        }
+        .clamp(-1.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * samples.1;
+        let local = Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        let wi = (-*wo).from_local(&local);
+        (wi, self.hg(cos_theta))
+    }
+
+    fn hg(&self, cos_theta: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::sampling::uniform_sample_sphere;
+
+    /// Low discrepancy points in `[0, 1)^2`.
+    fn samples(n: usize) -> impl Iterator<Item = (f64, f64)> {
+        (0..n).map(move |i| {
+            (
+                (i as f64 + 0.5) / n as f64,
+                (i as f64 * 0.618_033_988_75).fract(),
+            )
+        })
+    }
+
+    #[test]
+    fn test_sample_p_agrees_with_p() {
+        let wo = Vec3::new(0.3, -0.4, 0.5).normalize();
+        for g in [-0.7, 0.0, 0.4, 0.9] {
+            let phase = HenyeyGreenstein::new(g);
+            let n = 10000;
+            let mut mean_cos = 0.0;
+            for u in samples(n) {
+                let (wi, pdf) = phase.sample_p(&wo, u);
+                assert!((wi.length() - 1.0).abs() < 1e-9);
+                assert!((pdf - phase.p(&wo, &wi)).abs() < 1e-9 * pdf.max(1.0));
+                mean_cos += -wo.dot(&wi);
+            }
+            // The mean cosine to the direction of propagation is `g`.
+            assert!((mean_cos / n as f64 - g).abs() < 0.01, "{g}");
+
+            // `p` is normalized over the sphere.
+            let integral: f64 = samples(n)
+                .map(|u| phase.p(&wo, &uniform_sample_sphere(u)) * 4.0 * PI)
+                .sum();
+            assert!((integral / n as f64 - 1.0).abs() < 0.02, "{g}");
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    math::{transform::Transform, vec3::Vec3, vec4::Vec4},
-    render::film::Film,
+    math::{matrix4::Matrix4, transform::Transform, vec3::Vec3},
+    render::{colour::Colour, film::Film},
 };
 
 use super::ray::Ray;
@@ -14,6 +14,23 @@ pub struct Camera {
     camera_to_world: Transform,
     raster_to_camera: Transform,
     film: Film,
+
+    /// Area of the image plane at distance one from the eye.
+    image_area: f64,
+}
+
+/// A point on the camera sampled towards a point in the scene.
+pub struct CameraSample {
+    /// Unit direction from the reference point towards the eye.
+    pub wi: Vec3,
+    /// Importance carried towards the reference point.
+    pub we: Colour,
+    /// Solid angle density of `wi` as seen from the reference point.
+    pub pdf: f64,
+    /// Position of the eye.
+    pub p: Vec3,
+    /// Raster position the reference point projects onto.
+    pub raster: (f64, f64),
 }
 
 impl Camera {
@@ -21,6 +38,9 @@ impl Camera {
     /// g: the gaze direction,
     /// t: the upward direction
     /// f: focus distance
+    ///
+    /// The image plane sits at distance `f` in front of the eye and spans
+    /// `[-1, 1]` vertically, so `f` controls the field of view.
     pub fn new(
         e: Vec3,
         g: Vec3,
@@ -33,25 +53,196 @@ impl Camera {
         let u = t.cross(&w).normalized();
         let v = w.cross(&u);
         let camera_to_world = Transform::new(e, u, v, w);
-        let raster_to_camera = Transform::new_identity();
+        let raster_to_camera = Transform::from_matrix(raster_to_camera(f, resolution));
         let film = Film::new(resolution, filename);
+        let aspect = resolution.0 as f64 / resolution.1 as f64;
         Self {
             f,
             e,
             camera_to_world,
             raster_to_camera,
             film,
+            image_area: 4.0 * aspect / (f * f),
         }
     }
 
-    pub fn get_camera_sample(&self, raster: (usize, usize)) -> Option<Ray> {
+    pub fn position(&self) -> Vec3 {
+        self.e
+    }
+
+    /// Unit viewing direction.
+    pub fn forward(&self) -> Vec3 {
+        self.camera_to_world
+            .apply_vector(&Vec3::new(0.0, 0.0, -1.0))
+            .normalize()
+    }
+
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+
+    pub fn film_mut(&mut self) -> &mut Film {
+        &mut self.film
+    }
+
+    /// Generate the primary ray through `raster`, offset within the pixel by
+    /// `sample` in `[0, 1)^2`.
+    pub fn get_camera_sample(&self, raster: (usize, usize), sample: (f64, f64)) -> Option<Ray> {
         if raster.0 >= self.film.resolution.0 || raster.1 >= self.film.resolution.1 {
             return None;
         }
-        let r = Vec3::new(raster.0 as f64, raster.1 as f64 , 0.0);
-        let p_camera = self.raster_to_camera.mat * r;
-        let p = self.camera_to_world.mat * p_camera;
-        let d = - (self.e - p);
-        Some(Ray::new(p, d))
+        let r = Vec3::new(raster.0 as f64 + sample.0, raster.1 as f64 + sample.1, 0.0);
+        let p_camera = self.raster_to_camera.apply_point(&r);
+        let d = self.camera_to_world.apply_vector(&p_camera).normalize();
+        Some(Ray::new(self.e, d))
+    }
+
+    /// The continuous raster position that direction `d` leaving the eye
+    /// passes through, if it lands on the film.
+    pub fn raster_from_direction(&self, d: &Vec3) -> Option<(f64, f64)> {
+        let d_camera = self.camera_to_world.apply_inv_vector(d);
+        if d_camera.z >= 0.0 {
+            return None;
+        }
+        let p_camera = d_camera * (-self.f / d_camera.z);
+        let raster = self.raster_to_camera.apply_inv_point(&p_camera);
+        let (width, height) = self.film.resolution;
+        if raster.x < 0.0 || raster.x >= width as f64 || raster.y < 0.0 || raster.y >= height as f64
+        {
+            return None;
+        }
+        Some((raster.x, raster.y))
+    }
+
+    /// Importance emitted along the ray leaving the eye in direction `d`,
+    /// and the raster position it passes through.
+    pub fn we(&self, d: &Vec3) -> (Colour, Option<(f64, f64)>) {
+        let d = d.normalize();
+        let cos_theta = d.dot(&self.forward());
+        let Some(raster) = self.raster_from_direction(&d) else {
+            return (Colour::new(), None);
+        };
+        let cos2 = cos_theta * cos_theta;
+        (
+            Colour::grey(1.0 / (self.image_area * cos2 * cos2)),
+            Some(raster),
+        )
+    }
+
+    /// Positional and directional densities of generating a primary ray
+    /// leaving the eye in direction `d`.
+    pub fn pdf_we(&self, d: &Vec3) -> (f64, f64) {
+        let d = d.normalize();
+        let cos_theta = d.dot(&self.forward());
+        if self.raster_from_direction(&d).is_none() {
+            return (0.0, 0.0);
+        }
+        (
+            1.0,
+            1.0 / (self.image_area * cos_theta * cos_theta * cos_theta),
+        )
+    }
+
+    /// Connect the point `p` to the eye.
+    pub fn sample_wi(&self, p: &Vec3) -> Option<CameraSample> {
+        let d = self.e - *p;
+        let dist_squared = d.length_squared();
+        if dist_squared == 0.0 {
+            return None;
+        }
+        let wi = d / dist_squared.sqrt();
+        let (we, raster) = self.we(&-wi);
+        let raster = raster?;
+        let cos_theta = wi.dot(&self.forward()).abs();
+        Some(CameraSample {
+            wi,
+            we,
+            pdf: dist_squared / cos_theta,
+            p: self.e,
+            raster,
+        })
+    }
+}
+
+/// Map raster coordinate `(x, y, 0)` onto the image plane `z = -f` in camera
+/// space, with `y` pointing down in raster space and up in camera space.
+#[rustfmt::skip]
+fn raster_to_camera(f: f64, resolution: (usize, usize)) -> Matrix4 {
+    let (w, h) = (resolution.0 as f64, resolution.1 as f64);
+    let aspect = w / h;
+    Matrix4::new([
+        2.0 * aspect / w, 0.0,      0.0, -aspect,
+        0.0,              -2.0 / h, 0.0, 1.0,
+        0.0,              0.0,      1.0, -f,
+        0.0,              0.0,      0.0, 1.0,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::math::sampling::uniform_sample_sphere;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Vec3::new(1.0, -4.0, 2.0),
+            Vec3::new(-0.2, 1.0, -0.3),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.2,
+            (8, 6),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_raster_from_direction_inverts_camera_sample() {
+        let camera = camera();
+        for (pixel, u) in [
+            ((0, 0), (0.1, 0.2)),
+            ((3, 4), (0.5, 0.5)),
+            ((7, 5), (0.9, 0.99)),
+        ] {
+            let ray = camera.get_camera_sample(pixel, u).unwrap();
+            let (x, y) = camera.raster_from_direction(&ray.d).unwrap();
+            assert!((x - (pixel.0 as f64 + u.0)).abs() < 1e-9, "{x}");
+            assert!((y - (pixel.1 as f64 + u.1)).abs() < 1e-9, "{y}");
+        }
+        assert!(camera.raster_from_direction(&-camera.forward()).is_none());
+    }
+
+    #[test]
+    fn test_pdf_we_is_consistent_with_we() {
+        let camera = camera();
+        let forward = camera.forward();
+        let n = 200000;
+        let mut integral = 0.0;
+        for i in 0..n {
+            let u = (
+                (i as f64 + 0.5) / n as f64,
+                (i as f64 * 0.618_033_988_75).fract(),
+            );
+            let d = uniform_sample_sphere(u);
+            let (we, raster) = camera.we(&d);
+            let (pdf_pos, pdf_dir) = camera.pdf_we(&d);
+            if raster.is_none() {
+                assert!(we.is_black());
+                assert_eq!((pdf_pos, pdf_dir), (0.0, 0.0));
+                continue;
+            }
+            // The pinhole is a single point, and directions are sampled
+            // proportionally to the importance times the cosine.
+            assert_eq!(pdf_pos, 1.0);
+            let cos_theta = d.dot(&forward);
+            assert!((pdf_dir - we.r * cos_theta).abs() < 1e-9 * pdf_dir);
+            integral += pdf_dir * 4.0 * PI;
+        }
+        // The directional density is normalized over the field of view.
+        assert!(
+            (integral / n as f64 - 1.0).abs() < 0.01,
+            "{}",
+            integral / n as f64
+        );
     }
 }
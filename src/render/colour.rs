@@ -1,4 +1,6 @@
-#[derive(Clone, Debug, Default)]
+use std::ops::{Add, AddAssign, Div, Index, Mul, MulAssign, Sub};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Colour {
     pub r: f64,
     pub g: f64,
@@ -14,6 +16,48 @@ impl Colour {
         }
     }
 
+    pub const fn rgb(r: f64, g: f64, b: f64) -> Self {
+        Self { r, g, b }
+    }
+
+    pub const fn grey(v: f64) -> Self {
+        Self::rgb(v, v, v)
+    }
+
+    pub fn is_black(&self) -> bool {
+        self.r == 0.0 && self.g == 0.0 && self.b == 0.0
+    }
+
+    pub fn has_nans(&self) -> bool {
+        self.r.is_nan() || self.g.is_nan() || self.b.is_nan()
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    /// Average of the three channels.
+    pub fn average(&self) -> f64 {
+        (self.r + self.g + self.b) / 3.0
+    }
+
+    /// Relative luminance (Rec. 709 weights).
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Gamma encode and clamp into the displayable range `[0, 1)`.
+    pub fn to_display(&self) -> Self {
+        let encode = |c: f64| {
+            if c.is_nan() {
+                0.0
+            } else {
+                c.max(0.0).powf(1.0 / 2.2).min(0.999)
+            }
+        };
+        Self::rgb(encode(self.r), encode(self.g), encode(self.b))
+    }
+
     pub fn validate_colour(&self) -> bool {
         0.0 <= self.r && self.r < 1.0
           && 0.0 <= self.g && self.g < 1.0
@@ -29,3 +73,91 @@ impl Colour {
         ]
     }
 }
+
+impl Add<Colour> for Colour {
+    type Output = Colour;
+    fn add(self, rhs: Colour) -> Self::Output {
+        Colour::rgb(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
+    }
+}
+
+impl AddAssign<Colour> for Colour {
+    fn add_assign(&mut self, rhs: Colour) {
+        self.r += rhs.r;
+        self.g += rhs.g;
+        self.b += rhs.b;
+    }
+}
+
+impl Sub<Colour> for Colour {
+    type Output = Colour;
+    fn sub(self, rhs: Colour) -> Self::Output {
+        Colour::rgb(self.r - rhs.r, self.g - rhs.g, self.b - rhs.b)
+    }
+}
+
+impl Mul<Colour> for Colour {
+    type Output = Colour;
+    fn mul(self, rhs: Colour) -> Self::Output {
+        Colour::rgb(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
+    }
+}
+
+impl MulAssign<Colour> for Colour {
+    fn mul_assign(&mut self, rhs: Colour) {
+        self.r *= rhs.r;
+        self.g *= rhs.g;
+        self.b *= rhs.b;
+    }
+}
+
+impl Mul<f64> for Colour {
+    type Output = Colour;
+    fn mul(self, rhs: f64) -> Self::Output {
+        Colour::rgb(self.r * rhs, self.g * rhs, self.b * rhs)
+    }
+}
+
+impl Mul<Colour> for f64 {
+    type Output = Colour;
+    fn mul(self, rhs: Colour) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl MulAssign<f64> for Colour {
+    fn mul_assign(&mut self, rhs: f64) {
+        self.r *= rhs;
+        self.g *= rhs;
+        self.b *= rhs;
+    }
+}
+
+impl Div<f64> for Colour {
+    type Output = Colour;
+    fn div(self, rhs: f64) -> Self::Output {
+        debug_assert!(rhs != 0.0, "Division by zero");
+        let inv = 1.0 / rhs;
+        self * inv
+    }
+}
+
+impl Div<Colour> for Colour {
+    type Output = Colour;
+    fn div(self, rhs: Colour) -> Self::Output {
+        let div = |a: f64, b: f64| if b != 0.0 { a / b } else { 0.0 };
+        Colour::rgb(div(self.r, rhs.r), div(self.g, rhs.g), div(self.b, rhs.b))
+    }
+}
+
+impl Index<usize> for Colour {
+    type Output = f64;
+    fn index(&self, i: usize) -> &Self::Output {
+        match i {
+            0 => &self.r,
+            1 => &self.g,
+            2 => &self.b,
+            _ => panic!("Index out of bounds for Colour: {}", i),
+        }
+    }
+}
@@ -1,18 +1,128 @@
-struct Pixel([f32; 3]);
+use std::path::Path;
+
+use crate::render::{colour::Colour, image::Image};
+
+/// Running sum of the radiance samples that landed in a pixel.
+#[derive(Clone, Copy, Default)]
+struct Pixel {
+    rgb: [f64; 3],
+    weight: f64,
+    /// Contributions splatted onto the pixel by light paths, which are not
+    /// normalized by the pixel weight.
+    splat: [f64; 3],
+}
 
 pub struct Film {
     pub(crate) resolution: (usize, usize),
     pub(crate) filename: String,
-    pub(crate) pixels: Vec<Pixel>,
+    pixels: Vec<Pixel>,
+    /// Scale applied to the splats when the image is resolved.
+    splat_scale: f64,
 }
 
 impl Film {
     pub fn new(resolution: (usize, usize), filename: String) -> Self {
-        let pixels = Vec::with_capacity(resolution.0 * resolution.1);
+        let pixels = vec![Pixel::default(); resolution.0 * resolution.1];
         Self {
             resolution,
             filename,
-            pixels
+            pixels,
+            splat_scale: 1.0,
+        }
+    }
+
+    pub fn resolution(&self) -> (usize, usize) {
+        self.resolution
+    }
+
+    /// An empty film of the same resolution that is saved next to this one,
+    /// with `suffix` appended to the file stem.
+    pub fn sibling(&self, suffix: &str) -> Film {
+        let path = Path::new(&self.filename);
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("output");
+        let filename = path
+            .with_file_name(format!("{stem}_{suffix}.ppm"))
+            .to_string_lossy()
+            .into_owned();
+        Film::new(self.resolution, filename)
+    }
+
+    /// Accumulate one radiance sample for the pixel at `raster`.
+    pub fn add_sample(&mut self, raster: (usize, usize), colour: &Colour) {
+        debug_assert!(raster.0 < self.resolution.0 && raster.1 < self.resolution.1);
+        let pixel = &mut self.pixels[raster.1 * self.resolution.0 + raster.0];
+        pixel.rgb[0] += colour.r;
+        pixel.rgb[1] += colour.g;
+        pixel.rgb[2] += colour.b;
+        pixel.weight += 1.0;
+    }
+
+    /// Add a contribution to whichever pixel contains the continuous raster
+    /// position `raster`.
+    pub fn add_splat(&mut self, raster: (f64, f64), colour: &Colour) {
+        if raster.0 < 0.0 || raster.1 < 0.0 {
+            return;
+        }
+        let (x, y) = (raster.0 as usize, raster.1 as usize);
+        if x >= self.resolution.0 || y >= self.resolution.1 || colour.has_nans() {
+            return;
+        }
+        let pixel = &mut self.pixels[y * self.resolution.0 + x];
+        pixel.splat[0] += colour.r;
+        pixel.splat[1] += colour.g;
+        pixel.splat[2] += colour.b;
+    }
+
+    /// Set the scale applied to splats, usually one over the number of
+    /// light paths traced per pixel.
+    pub fn set_splat_scale(&mut self, splat_scale: f64) {
+        self.splat_scale = splat_scale;
+    }
+
+    /// The current estimate of the pixel at `raster`.
+    pub fn get_pixel(&self, raster: (usize, usize)) -> Colour {
+        let pixel = &self.pixels[raster.1 * self.resolution.0 + raster.0];
+        let splat = Colour::rgb(pixel.splat[0], pixel.splat[1], pixel.splat[2]) * self.splat_scale;
+        if pixel.weight == 0.0 {
+            return splat;
+        }
+        Colour::rgb(pixel.rgb[0], pixel.rgb[1], pixel.rgb[2]) / pixel.weight + splat
+    }
+
+    /// Replace the estimate of the pixel at `raster` with `colour`, for
+    /// images computed from the samples rather than averaging them.
+    pub fn set_pixel(&mut self, raster: (usize, usize), colour: &Colour) {
+        self.pixels[raster.1 * self.resolution.0 + raster.0] = Pixel {
+            rgb: [colour.r, colour.g, colour.b],
+            weight: 1.0,
+            splat: [0.0; 3],
+        };
+    }
+
+    /// The current estimate of every pixel, row by row from the top.
+    pub fn pixels(&self) -> Vec<Colour> {
+        (0..self.resolution.1)
+            .flat_map(|y| (0..self.resolution.0).map(move |x| (x, y)))
+            .map(|raster| self.get_pixel(raster))
+            .collect()
+    }
+
+    /// Resolve the accumulated samples into a displayable image.
+    pub fn to_image(&self) -> Image {
+        let mut image = Image::new(self.resolution);
+        for y in 0..self.resolution.1 {
+            for x in 0..self.resolution.0 {
+                image.set_colour(&(x, y), self.get_pixel((x, y)).to_display());
+            }
         }
+        image
     }
-}
\ No newline at end of file
+
+    /// Write the image to `filename`.
+    pub fn save(&self) -> std::io::Result<()> {
+        self.to_image().save_to_file(Path::new(&self.filename))
+    }
+}
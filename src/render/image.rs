@@ -11,12 +11,14 @@ pub struct Image {
 
 impl Image {
     pub fn new(resolution: (usize, usize)) -> Self {
-        let pixels = Vec::with_capacity(resolution.0 * resolution.1);
+        let pixels = vec![Colour::new(); resolution.0 * resolution.1];
         Self { resolution, pixels }
     }
 
+    /// Set the colour of the pixel at `(x, y)`, where `(0, 0)` is the top
+    /// left corner.
     pub fn set_colour(&mut self, pixel: &(usize, usize), colour: Colour) {
-        self.pixels[pixel.0 * self.resolution.0 + pixel.1] = colour;
+        self.pixels[pixel.1 * self.resolution.0 + pixel.0] = colour;
     }
 
     pub fn save_to_file(self, path: &Path) -> std::io::Result<()> {
@@ -3,3 +3,4 @@ pub mod colour;
 pub mod film;
 pub mod image;
 pub mod ray;
+pub mod renderer;
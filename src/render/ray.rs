@@ -1,5 +1,6 @@
 use crate::math::{transform::Transform, vec3::Vec3};
 
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub p: Vec3,
     pub d: Vec3,
@@ -14,19 +15,11 @@ impl Ray {
         self.p + *t * self.d
     }
 
+    /// Bring the ray into the local space of `transform`. The direction is
+    /// left unnormalized so that `t` means the same thing in both spaces.
     pub fn apply_inv(&self, transform: &Transform) -> Self {
-        let p = (transform.inv * self.p.to_homo()).to_inhomo();
-        let d = Vec3::new(
-            transform.inv[0][0] * self.d.x
-                + transform.inv[0][1] * self.d.x
-                + transform.inv[0][1] * self.d.z,
-            transform.inv[1][0] * self.d.x
-                + transform.inv[1][1] * self.d.x
-                + transform.inv[1][1] * self.d.z,
-            transform.inv[2][0] * self.d.x
-                + transform.inv[2][1] * self.d.x
-                + transform.inv[2][1] * self.d.z,
-        );
+        let p = transform.apply_inv_point(&self.p);
+        let d = transform.apply_inv_vector(&self.d);
         Self { p, d }
     }
 }
@@ -0,0 +1,49 @@
+use crate::{
+    common::sampler::Sampler, integrator::Integrator, render::camera::Camera, scene::Scene,
+};
+
+/// Drive an integrator over every pixel of the camera's film.
+pub struct Renderer {
+    /// Number of camera samples taken per pixel.
+    pub spp: usize,
+}
+
+impl Renderer {
+    pub fn new(spp: usize) -> Self {
+        Self { spp }
+    }
+
+    /// Render the scene into the camera's film with `integrator`.
+    pub fn render(
+        &self,
+        integrator: &dyn Integrator,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        integrator.render(self, scene, camera, sampler);
+    }
+
+    /// Average `li` over `spp` camera rays for every pixel.
+    pub fn render_pixels<I: Integrator + ?Sized>(
+        &self,
+        integrator: &I,
+        scene: &Scene,
+        camera: &mut Camera,
+        sampler: &mut dyn Sampler,
+    ) {
+        let (width, height) = camera.film().resolution();
+        for y in 0..height {
+            for x in 0..width {
+                for index in 0..self.spp {
+                    sampler.start_pixel_sample((x, y), index);
+                    let Some(ray) = camera.get_camera_sample((x, y), sampler.get_2d()) else {
+                        continue;
+                    };
+                    let l = integrator.li(&ray, scene, sampler);
+                    camera.film_mut().add_sample((x, y), &l);
+                }
+            }
+        }
+    }
+}
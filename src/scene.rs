@@ -1,17 +1,21 @@
 use std::sync::Arc;
 
 use crate::{
-    light::Light,
+    accel::aabb::AABB,
+    light::{Light, area::AreaLight},
     material::Material,
-    math::transform::Transform,
-    render::ray::Ray,
-    shape::{HitRecord, primitive::Primitive, sphere::Sphere},
+    math::{matrix4::Matrix4, transform::Transform, vec3::Vec3},
+    medium::Medium,
+    render::{colour::Colour, ray::Ray},
+    shape::{HitRecord, RAY_EPSILON, primitive::Primitive, sphere::Sphere},
 };
 
 #[derive(Default)]
 pub struct Scene {
     primitives: Vec<Primitive>,
     lights: Vec<Arc<dyn Light>>,
+    /// Medium filling the space outside every primitive, where the camera is.
+    medium: Option<Arc<dyn Medium>>,
     // bvh: Option<Box<BVHNode>>,
 }
 
@@ -20,21 +24,117 @@ impl Scene {
         Self {
             primitives: Vec::new(),
             lights: Vec::new(),
+            medium: None,
         }
     }
 
-    pub fn find_first_hit(&self, ray: &Ray) -> Option<HitRecord> {
+    /// Add a primitive to the scene. The parts of the primitive with an
+    /// emissive material are registered as area lights as well, which
+    /// requires shapes with several materials to split into parts.
+    pub fn add_primitive(&mut self, mut primitive: Primitive) {
+        let materials = primitive.material_table();
+        primitive.area_lights = vec![None; materials.len()];
+        for (slot, material) in materials.iter().enumerate() {
+            if !material.is_emissive() {
+                continue;
+            }
+            let shape = if primitive.materials.is_empty() {
+                primitive.shape.clone()
+            } else {
+                primitive
+                    .shape
+                    .material_part(&|index| primitive.material_slot(index) == slot)
+                    .expect("Emissive materials need a shape that splits into parts")
+            };
+            if shape.surface_area() == 0.0 {
+                continue;
+            }
+            let light = Arc::new(AreaLight::new(
+                shape,
+                primitive.transform.clone(),
+                material.emission(),
+            ));
+            primitive.area_lights[slot] = Some(light.clone());
+            self.lights.push(light);
+        }
+        self.primitives.push(primitive);
+    }
+
+    pub fn add_light(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn primitives(&self) -> &[Primitive] {
+        &self.primitives
+    }
+
+    pub fn lights(&self) -> &[Arc<dyn Light>] {
+        &self.lights
+    }
+
+    /// Bounds of all the bounded primitives.
+    pub fn bounds(&self) -> AABB {
+        self.primitives
+            .iter()
+            .map(|primitive| primitive.bounds())
+            .filter(|bounds| bounds.is_finite())
+            .fold(AABB::empty(), |acc, bounds| acc.union(&bounds))
+    }
+
+    pub fn set_medium(&mut self, medium: Arc<dyn Medium>) {
+        self.medium = Some(medium);
+    }
+
+    pub fn medium(&self) -> Option<&Arc<dyn Medium>> {
+        self.medium.as_ref()
+    }
+
+    pub fn find_first_hit(&self, ray: &Ray) -> Option<HitRecord<'_>> {
         self.primitives
             .iter()
             .filter_map(|primitive| primitive.intersect(ray))
             .min_by(|p1, p2| p1.t.total_cmp(&p2.t))
     }
 
+    /// Whether nothing blocks `ray` before it reaches `t = 1`.
+    pub fn unoccluded(&self, ray: &Ray) -> bool {
+        let t_max = 1.0 - RAY_EPSILON;
+        !self
+            .primitives
+            .iter()
+            .filter_map(|primitive| primitive.intersect(ray))
+            .any(|hit| hit.t < t_max)
+    }
+
     pub fn dummy(&mut self) {
-        self.primitives.push(Primitive {
-            shape: Arc::new(Sphere::new(1.0)),
-            transform: Transform::default(),
-            material: Arc::new(Material::default()),
-        });
+        self.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            Transform::default(),
+            Arc::new(Material::diffuse(Colour::grey(0.8))),
+        ));
+        self.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1000.0)),
+            translate(Vec3::new(0.0, 0.0, -1001.0)),
+            Arc::new(Material::diffuse(Colour::rgb(0.4, 0.5, 0.4))),
+        ));
+        self.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.6)),
+            translate(Vec3::new(1.6, -1.6, -0.4)),
+            Arc::new(Material::dielectric(1.5)),
+        ));
+        self.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(1.0)),
+            translate(Vec3::new(-1.0, 2.2, 0.0)),
+            Arc::new(Material::metal(Colour::rgb(0.9, 0.8, 0.6), 0.0)),
+        ));
+        self.add_primitive(Primitive::new(
+            Arc::new(Sphere::new(0.5)),
+            translate(Vec3::new(1.0, -3.0, 4.0)),
+            Arc::new(Material::light(Colour::grey(40.0))),
+        ));
     }
 }
+
+fn translate(v: Vec3) -> Transform {
+    Transform::from_matrix(Matrix4::new_translate(v.into()))
+}
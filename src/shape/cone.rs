@@ -0,0 +1,166 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quadratic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample, sweep_phi},
+};
+
+/// A canonical cone around the z axis with its base of radius `r` at
+/// `z = 0` and its apex at `z = h`, cut to `z_min <= z <= z_max` and swept
+/// from `phi = 0` to `phi_max`. Cutting off the apex gives a lamp shade.
+pub struct Cone {
+    r: f64,
+    h: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+}
+
+impl Cone {
+    pub fn new(r: f64, h: f64) -> Self {
+        assert!(
+            r > 0.0 && h > 0.0,
+            "Cone radius and height must be positive"
+        );
+        Self {
+            r,
+            h,
+            z_min: 0.0,
+            z_max: h,
+            phi_max: 2.0 * PI,
+        }
+    }
+
+    /// Only keep the part of the cone between `z_min` and `z_max`.
+    pub fn with_z_range(mut self, z_min: f64, z_max: f64) -> Self {
+        self.z_min = z_min.min(z_max).clamp(0.0, self.h);
+        self.z_max = z_min.max(z_max).clamp(0.0, self.h);
+        self
+    }
+
+    /// Only sweep the cone up to the angle `phi_max`, in radians.
+    pub fn with_phi_max(mut self, phi_max: f64) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Outward normal at `p`, the gradient of `x^2 + y^2 - (r / h)^2 (h - z)^2`.
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        let k = (self.r / self.h).powi(2);
+        Vec3::new(p.x, p.y, k * (self.h - p.z)).normalize()
+    }
+}
+
+impl Shape for Cone {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let k = (self.r / self.h).powi(2);
+        let (o, d) = (ray.p, ray.d);
+        let oz = o.z - self.h;
+        let a = d.x * d.x + d.y * d.y - k * d.z * d.z;
+        let b = 2.0 * (d.x * o.x + d.y * o.y - k * d.z * oz);
+        let c = o.x * o.x + o.y * o.y - k * oz * oz;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > f64::EPSILON)
+            .find_map(|t| {
+                let p = ray.at(&t);
+                if p.z < self.z_min || p.z > self.z_max {
+                    return None;
+                }
+                let phi = sweep_phi(&p, self.phi_max)?;
+                let n = self.normal(&p);
+                Some(LocalHitRecord {
+                    t,
+                    p,
+                    n,
+                    ns: n,
+                    tangent: None,
+                    uv: (phi / self.phi_max, p.z / self.h),
+                    material_index: 0,
+                })
+            })
+    }
+}
+
+impl Sampleable for Cone {
+    /// The area element grows linearly with the distance `w = h - z` to the
+    /// apex, so `w^2` is sampled uniformly.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let (w0, w1) = (self.h - self.z_max, self.h - self.z_min);
+        let w = (w0 * w0 + samples.0 * (w1 * w1 - w0 * w0)).sqrt();
+        let rho = self.r * w / self.h;
+        let phi = samples.1 * self.phi_max;
+        let p = Vec3::new(rho * phi.cos(), rho * phi.sin(), self.h - w);
+        ShapeSample {
+            p,
+            n: self.normal(&p),
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        let (w0, w1) = (self.h - self.z_max, self.h - self.z_min);
+        let slope = self.r / self.h;
+        0.5 * self.phi_max * slope * (1.0 + slope * slope).sqrt() * (w1 * w1 - w0 * w0)
+    }
+}
+
+impl Boundable for Cone {
+    fn bounds(&self) -> AABB {
+        let rho = self.r * (1.0 - self.z_min / self.h);
+        AABB::new(
+            Vec3::new(-rho, -rho, self.z_min),
+            Vec3::new(rho, rho, self.z_max),
+        )
+    }
+}
+
+impl Geometry for Cone {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cone_intersection_and_normal() {
+        let cone = Cone::new(1.0, 2.0);
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 1.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = cone.intersect_local(&ray).unwrap();
+        assert!((hit.t - 1.5).abs() < 1e-12);
+        assert!((hit.p - Vec3::new(0.5, 0.0, 1.0)).length() < 1e-12);
+        // Perpendicular to the slope and pointing outwards.
+        assert!((hit.n - Vec3::new(2.0, 0.0, 1.0).normalize()).length() < 1e-12);
+        assert!(hit.uv.0.abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        // Above the apex, below the base and beside the cone.
+        for ray in [
+            Ray::new(Vec3::new(2.0, 0.0, 2.5), Vec3::new(-1.0, 0.0, 0.0)),
+            Ray::new(Vec3::new(2.0, 0.0, -0.5), Vec3::new(-1.0, 0.0, 0.0)),
+            Ray::new(Vec3::new(2.0, 1.5, 0.5), Vec3::new(-1.0, 0.0, 0.0)),
+        ] {
+            assert!(cone.intersect_local(&ray).is_none());
+        }
+    }
+
+    #[test]
+    fn test_cone_phi_max_cut() {
+        let cone = Cone::new(1.0, 2.0).with_phi_max(PI / 2.0);
+        // The near side at phi = pi is cut away, the ray goes on to the
+        // inside of the far side at phi = 0.
+        let ray = Ray::new(Vec3::new(-2.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = cone.intersect_local(&ray).unwrap();
+        assert!((hit.t - 2.5).abs() < 1e-12);
+        assert!((hit.n - Vec3::new(2.0, 0.0, 1.0).normalize()).length() < 1e-12);
+
+        // Both crossings outside the sweep.
+        let ray = Ray::new(Vec3::new(-0.2, -2.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(cone.intersect_local(&ray).is_none());
+        // Inside the sweep.
+        let ray = Ray::new(Vec3::new(0.2, -2.0, 1.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = cone.intersect_local(&ray).unwrap();
+        assert!(hit.p.y > 0.0 && hit.uv.0 > 0.0 && hit.uv.0 < 1.0);
+    }
+}
@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use crate::{
+    accel::aabb::AABB,
+    material::Material,
+    math::{transform::Transform, vec3::Vec3},
+    render::ray::Ray,
+    shape::{
+        Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample, primitive::Primitive,
+    },
+};
+
+/// Boolean operation combining the solids of the two operands of a `Csg`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Points in either operand.
+    Union,
+    /// Points in both operands.
+    Intersection,
+    /// Points in the first operand but not in the second.
+    Difference,
+}
+
+impl CsgOperation {
+    fn contains(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_a || in_b,
+            CsgOperation::Intersection => in_a && in_b,
+            CsgOperation::Difference => in_a && !in_b,
+        }
+    }
+}
+
+/// A hit with an operand, in the space of the `Csg`.
+struct OperandHit {
+    hit: LocalHitRecord,
+    /// 0 for the first operand, 1 for the second.
+    operand: usize,
+    /// Whether the ray enters the operand there.
+    entering: bool,
+}
+
+/// Constructive solid geometry: the solid resulting from a boolean
+/// operation on two primitives, each with its own transform and materials.
+/// The operands should be closed shapes with outward normals, which can be
+/// `Csg`s themselves.
+///
+/// A ray is intersected with both operands along its whole length, and the
+/// hits where it passes in or out of the result are kept. Their normals are
+/// flipped to face out of the result, as on the surfaces the difference
+/// carves out of the first operand.
+pub struct Csg {
+    operation: CsgOperation,
+    operands: [Primitive; 2],
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, a: Primitive, b: Primitive) -> Self {
+        Self {
+            operation,
+            operands: [a, b],
+        }
+    }
+
+    /// Wrap the solid in a primitive with `transform` that gives every part
+    /// of it the material of the operand it comes from. The parts of a
+    /// `Csg` cannot be sampled on their own, so none of them may emit light.
+    pub fn into_primitive(self, transform: Transform) -> Primitive {
+        let materials = self.material_table();
+        assert!(
+            materials.iter().all(|material| !material.is_emissive()),
+            "CSG operands cannot have emissive materials"
+        );
+        let material = materials[0].clone();
+        Primitive::new(Arc::new(self), transform, material).with_materials(materials)
+    }
+
+    /// The material tables of the operands one after the other. The material
+    /// indices of the hits with the second operand are offset by the length
+    /// of the table of the first.
+    fn material_table(&self) -> Vec<Arc<Material>> {
+        self.operands
+            .iter()
+            .flat_map(|operand| operand.material_table())
+            .collect()
+    }
+
+    /// Every hit of the ray with `operand`, in the space of the `Csg`.
+    fn operand_hits(&self, operand: usize, ray: &Ray) -> Vec<OperandHit> {
+        let primitive = &self.operands[operand];
+        let offset = if operand == 0 {
+            0
+        } else {
+            self.operands[0].materials.len() + 1
+        };
+        let local_ray = ray.apply_inv(&primitive.transform);
+        primitive
+            .shape
+            .intersect_all_local(&local_ray)
+            .into_iter()
+            .map(|local_hit| {
+                let n = primitive.transform.apply_normal(&local_hit.n).normalize();
+                OperandHit {
+                    entering: n.dot(&ray.d) < 0.0,
+                    hit: LocalHitRecord {
+                        t: local_hit.t,
+                        p: ray.at(&local_hit.t),
+                        n,
+                        ns: primitive.transform.apply_normal(&local_hit.ns).normalize(),
+                        tangent: local_hit
+                            .tangent
+                            .map(|tangent| primitive.transform.apply_vector(&tangent)),
+                        uv: local_hit.uv,
+                        material_index: offset + primitive.material_slot(local_hit.material_index),
+                    },
+                    operand,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `p` is inside `operand`, from the parity of the number of
+    /// times a ray leaving `p` crosses its surface.
+    fn inside(&self, operand: usize, p: &Vec3) -> bool {
+        let d = Vec3::new(0.5773, 0.5774, 0.5772);
+        self.operand_hits(operand, &Ray::new(*p, d))
+            .first()
+            .is_some_and(|first| !first.entering)
+    }
+}
+
+impl Shape for Csg {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        self.intersect_all_local(ray).into_iter().next()
+    }
+
+    fn intersect_all_local(&self, ray: &Ray) -> Vec<LocalHitRecord> {
+        let mut hits = self.operand_hits(0, ray);
+        let hits_b = self.operand_hits(1, ray);
+        // The ray starts inside an operand if it first leaves it.
+        let mut inside = [
+            hits.first().is_some_and(|hit| !hit.entering),
+            hits_b.first().is_some_and(|hit| !hit.entering),
+        ];
+        hits.extend(hits_b);
+        hits.sort_by(|a, b| a.hit.t.total_cmp(&b.hit.t));
+
+        let mut result = Vec::new();
+        let mut in_result = self.operation.contains(inside[0], inside[1]);
+        for OperandHit {
+            mut hit, operand, ..
+        } in hits
+        {
+            inside[operand] = !inside[operand];
+            let now_in_result = self.operation.contains(inside[0], inside[1]);
+            if now_in_result == in_result {
+                continue;
+            }
+            in_result = now_in_result;
+            // Entering the result, the normal must face the ray.
+            if (hit.n.dot(&ray.d) < 0.0) != in_result {
+                hit.n = -hit.n;
+                hit.ns = -hit.ns;
+            }
+            result.push(hit);
+        }
+        result
+    }
+}
+
+impl Sampleable for Csg {
+    /// Sample an operand proportionally to its area, then a point on it. The
+    /// sample fails with a zero pdf when the point is not on the surface of
+    /// the result, so the density of the successful samples is relative to
+    /// the area of both operands.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let areas = self
+            .operands
+            .each_ref()
+            .map(|operand| operand.shape.surface_area());
+        let total = areas[0] + areas[1];
+        let pick_a = samples.0 * total < areas[0];
+        let (operand, u) = if pick_a {
+            (0, samples.0 * total / areas[0])
+        } else {
+            (1, (samples.0 * total - areas[0]) / areas[1])
+        };
+        let primitive = &self.operands[operand];
+        let sample = primitive
+            .shape
+            .sample_uniform(&(u.clamp(0.0, 1.0), samples.1));
+        let p = primitive.transform.apply_point(&sample.p);
+        let mut n = primitive.transform.apply_normal(&sample.n).normalize();
+        let other = self.inside(1 - operand, &p);
+        let on_surface = match self.operation {
+            CsgOperation::Union => !other,
+            CsgOperation::Intersection => other,
+            CsgOperation::Difference => (operand == 0) != other,
+        };
+        if self.operation == CsgOperation::Difference && operand == 1 {
+            n = -n;
+        }
+        let pdf = if on_surface {
+            areas[operand] / total * sample.pdf / primitive.transform.area_scale(&sample.n)
+        } else {
+            0.0
+        };
+        ShapeSample { p, n, pdf }
+    }
+
+    /// The area of both operands, which the samples are drawn from.
+    fn surface_area(&self) -> f64 {
+        self.operands
+            .iter()
+            .map(|operand| operand.shape.surface_area())
+            .sum()
+    }
+
+    /// Solid angle density of sampling the point hit from `p`, assuming the
+    /// operands sample their surfaces uniformly.
+    fn pdf(&self, p: &Vec3, w_i: &Vec3) -> f64 {
+        let ray = Ray::new(*p, *w_i);
+        let Some(hit) = self.intersect_local(&ray) else {
+            return 0.0;
+        };
+        // Find the operand the hit comes from to undo its transform.
+        let Some(operand) = (0..2).find(|&operand| {
+            self.operand_hits(operand, &ray)
+                .iter()
+                .any(|operand_hit| operand_hit.hit.t == hit.t)
+        }) else {
+            return 0.0;
+        };
+        let transform = &self.operands[operand].transform;
+        let area_pdf =
+            1.0 / (self.surface_area() * transform.area_scale(&transform.apply_inv_normal(&hit.n)));
+        let d = hit.p - *p;
+        let cos_theta = hit.n.dot(&d).abs() / d.length();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+        area_pdf * d.length_squared() / cos_theta
+    }
+}
+
+impl Boundable for Csg {
+    fn bounds(&self) -> AABB {
+        let [a, b] = self.operands.each_ref().map(|operand| operand.bounds());
+        match self.operation {
+            CsgOperation::Union => a.union(&b),
+            CsgOperation::Intersection => a.intersection(&b),
+            CsgOperation::Difference => a,
+        }
+    }
+}
+
+impl Geometry for Csg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        render::colour::Colour,
+        shape::{cube::Cube, sphere::Sphere},
+    };
+
+    #[test]
+    fn test_csg_operations_keep_result_boundaries() {
+        let red = Arc::new(Material::diffuse(Colour::rgb(1.0, 0.0, 0.0)));
+        let blue = Arc::new(Material::diffuse(Colour::rgb(0.0, 0.0, 1.0)));
+        let csg = |operation| {
+            let cube = Cube::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+            let a = Primitive::new(Arc::new(cube), Transform::new_identity(), red.clone());
+            let b = Primitive::new(
+                Arc::new(Sphere::new(0.5)),
+                Transform::new_identity(),
+                blue.clone(),
+            );
+            Csg::new(operation, a, b)
+        };
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let summary = |csg: &Csg| -> Vec<(f64, f64)> {
+            csg.intersect_all_local(&ray)
+                .iter()
+                .map(|hit| ((hit.t * 1e9).round() / 1e9, hit.n.x))
+                .collect()
+        };
+
+        // The cube with a spherical cavity: the cavity's normals face into it.
+        let difference = csg(CsgOperation::Difference);
+        assert_eq!(
+            summary(&difference),
+            vec![(4.0, -1.0), (4.5, 1.0), (5.5, -1.0), (6.0, 1.0)]
+        );
+        assert_eq!(
+            summary(&csg(CsgOperation::Union)),
+            vec![(4.0, -1.0), (6.0, 1.0)]
+        );
+        let intersection = csg(CsgOperation::Intersection);
+        assert_eq!(summary(&intersection), vec![(4.5, -1.0), (5.5, 1.0)]);
+
+        // Hits keep the material of their operand.
+        let primitive = difference.into_primitive(Transform::new_identity());
+        let hits: Vec<_> = (0..2)
+            .map(|i| {
+                primitive
+                    .intersect(&Ray::new(
+                        Vec3::new(-5.0 + i as f64 * 4.75, 0.0, 0.0),
+                        ray.d,
+                    ))
+                    .unwrap()
+            })
+            .collect();
+        assert!(std::ptr::eq(hits[0].material, red.as_ref()));
+        assert!(std::ptr::eq(hits[1].material, blue.as_ref()));
+
+        // Only the sphere survives the intersection, so only its samples do.
+        let accepted = (0..1000)
+            .filter(|i| {
+                intersection
+                    .sample_uniform(&((*i as f64 + 0.5) / 1000.0, 0.4))
+                    .pdf
+                    > 0.0
+            })
+            .count();
+        let sphere_area = std::f64::consts::PI;
+        let expected = 1000.0 * sphere_area / (24.0 + sphere_area);
+        assert!((accepted as f64 - expected).abs() <= 1.0, "{accepted}");
+    }
+}
@@ -1 +1,171 @@
-pub struct Cube {}
+use crate::{
+    accel::aabb::AABB,
+    math::vec3::Vec3,
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// An axis aligned box. Oriented boxes are boxes under the transform of
+/// their primitive.
+pub struct Cube {
+    bounds: AABB,
+}
+
+impl Cube {
+    /// The box spanned by two opposite corners, in any order.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            bounds: AABB::new(a, b),
+        }
+    }
+
+    /// The normal of the face perpendicular to `axis` on the side of `p`.
+    fn face_normal(&self, axis: usize, p: &Vec3) -> Vec3 {
+        let mut n = Vec3::zero();
+        n[axis] = if p[axis] < self.bounds.center()[axis] {
+            -1.0
+        } else {
+            1.0
+        };
+        n
+    }
+
+    /// Area of one of the two faces perpendicular to `axis`.
+    fn face_area(&self, axis: usize) -> f64 {
+        let d = self.bounds.diagonal();
+        d[(axis + 1) % 3] * d[(axis + 2) % 3]
+    }
+}
+
+impl Shape for Cube {
+    /// Slab method, keeping track of the axes of the planes the ray enters
+    /// and leaves the box through.
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let (mut t0, mut t1) = (f64::NEG_INFINITY, f64::INFINITY);
+        let (mut axis0, mut axis1) = (0, 0);
+        for i in 0..3 {
+            if ray.d[i] == 0.0 {
+                if ray.p[i] < self.bounds.min[i] || ray.p[i] > self.bounds.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let inv_d = 1.0 / ray.d[i];
+            let mut t_near = (self.bounds.min[i] - ray.p[i]) * inv_d;
+            let mut t_far = (self.bounds.max[i] - ray.p[i]) * inv_d;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            if t_near > t0 {
+                (t0, axis0) = (t_near, i);
+            }
+            if t_far < t1 {
+                (t1, axis1) = (t_far, i);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+
+        // From inside the box, the ray hits the face it leaves through.
+        let (t, axis) = if t0 > f64::EPSILON {
+            (t0, axis0)
+        } else if t1 > f64::EPSILON {
+            (t1, axis1)
+        } else {
+            return None;
+        };
+        let mut p = ray.at(&t);
+        p[axis] = if p[axis] < self.bounds.center()[axis] {
+            self.bounds.min[axis]
+        } else {
+            self.bounds.max[axis]
+        };
+        let n = self.face_normal(axis, &p);
+        let o = self.bounds.offset(&p);
+        Some(LocalHitRecord {
+            t,
+            p,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (o[(axis + 1) % 3], o[(axis + 2) % 3]),
+            material_index: 0,
+        })
+    }
+}
+
+impl Sampleable for Cube {
+    /// Pick a face proportionally to its area, then a point uniformly on it.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let area = self.surface_area();
+        let mut u = samples.0 * area;
+        let mut face = 5;
+        for i in 0..6 {
+            let face_area = self.face_area(i / 2);
+            if u < face_area || i == 5 {
+                face = i;
+                u = if face_area > 0.0 {
+                    (u / face_area).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                break;
+            }
+            u -= face_area;
+        }
+
+        let axis = face / 2;
+        let mut p = Vec3::zero();
+        p[axis] = if face % 2 == 0 {
+            self.bounds.min[axis]
+        } else {
+            self.bounds.max[axis]
+        };
+        for (j, s) in [((axis + 1) % 3, u), ((axis + 2) % 3, samples.1)] {
+            p[j] = self.bounds.min[j] + s * (self.bounds.max[j] - self.bounds.min[j]);
+        }
+        ShapeSample {
+            p,
+            n: self.face_normal(axis, &p),
+            pdf: 1.0 / area,
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        2.0 * (0..3).map(|axis| self.face_area(axis)).sum::<f64>()
+    }
+}
+
+impl Boundable for Cube {
+    fn bounds(&self) -> AABB {
+        self.bounds
+    }
+}
+
+impl Geometry for Cube {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cube_hits_faces_from_outside_and_inside() {
+        let cube = Cube::new(Vec3::new(-1.0, -2.0, -3.0), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(cube.surface_area(), 2.0 * (8.0 + 24.0 + 12.0));
+
+        let ray = Ray::new(Vec3::new(0.5, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cube.intersect_local(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 1.0, 0.0));
+        assert!((hit.uv.1 - 0.75).abs() < 1e-12);
+
+        let ray = Ray::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let hit = cube.intersect_local(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, -1.0));
+
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(cube.intersect_local(&ray).is_none());
+    }
+}
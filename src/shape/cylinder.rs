@@ -1 +1,220 @@
+use std::f64::consts::PI;
 
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quadratic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample, sweep_phi},
+};
+
+/// A canonical cylinder of radius `r` around the z axis between `z_min` and
+/// `z_max`, swept from `phi = 0` to `phi_max` around the axis. With caps, the
+/// ends are closed by disks (or by the sectors of disks of a partial sweep).
+pub struct Cylinder {
+    r: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+    caps: bool,
+}
+
+impl Cylinder {
+    pub fn new(r: f64, z_min: f64, z_max: f64) -> Self {
+        assert!(r > 0.0, "Cylinder radius must be positive");
+        Self {
+            r,
+            z_min: z_min.min(z_max),
+            z_max: z_min.max(z_max),
+            phi_max: 2.0 * PI,
+            caps: false,
+        }
+    }
+
+    /// Only sweep the cylinder up to the angle `phi_max`, in radians.
+    pub fn with_phi_max(mut self, phi_max: f64) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// Close the ends of the cylinder.
+    pub fn with_caps(mut self) -> Self {
+        self.caps = true;
+        self
+    }
+
+    fn side_area(&self) -> f64 {
+        self.phi_max * self.r * (self.z_max - self.z_min)
+    }
+
+    fn cap_area(&self) -> f64 {
+        if self.caps {
+            0.5 * self.phi_max * self.r * self.r
+        } else {
+            0.0
+        }
+    }
+
+    fn intersect_side(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let a = ray.d.x * ray.d.x + ray.d.y * ray.d.y;
+        if a == 0.0 {
+            return None;
+        }
+        let b = 2.0 * (ray.d.x * ray.p.x + ray.d.y * ray.p.y);
+        let c = ray.p.x * ray.p.x + ray.p.y * ray.p.y - self.r * self.r;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > f64::EPSILON)
+            .find_map(|t| {
+                let p = ray.at(&t);
+                if p.z < self.z_min || p.z > self.z_max {
+                    return None;
+                }
+                let phi = sweep_phi(&p, self.phi_max)?;
+                let n = Vec3::new(p.x, p.y, 0.0).normalize();
+                Some(LocalHitRecord {
+                    t,
+                    p,
+                    n,
+                    ns: n,
+                    tangent: None,
+                    uv: (
+                        phi / self.phi_max,
+                        (p.z - self.z_min) / (self.z_max - self.z_min),
+                    ),
+                    material_index: 0,
+                })
+            })
+    }
+
+    fn intersect_cap(&self, ray: &Ray, z: f64, n: Vec3) -> Option<LocalHitRecord> {
+        if ray.d.z == 0.0 {
+            return None;
+        }
+        let t = (z - ray.p.z) / ray.d.z;
+        if t <= f64::EPSILON {
+            return None;
+        }
+        let mut p = ray.at(&t);
+        p.z = z;
+        let rho = (p.x * p.x + p.y * p.y).sqrt();
+        if rho > self.r {
+            return None;
+        }
+        let phi = sweep_phi(&p, self.phi_max)?;
+        Some(LocalHitRecord {
+            t,
+            p,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (phi / self.phi_max, rho / self.r),
+            material_index: 0,
+        })
+    }
+}
+
+impl Shape for Cylinder {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let side = self.intersect_side(ray);
+        if !self.caps {
+            return side;
+        }
+        let bottom = self.intersect_cap(ray, self.z_min, Vec3::new(0.0, 0.0, -1.0));
+        let top = self.intersect_cap(ray, self.z_max, Vec3::new(0.0, 0.0, 1.0));
+        [side, bottom, top]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| a.t.total_cmp(&b.t))
+    }
+}
+
+impl Sampleable for Cylinder {
+    /// Pick the side or a cap proportionally to its area, then a point
+    /// uniformly on it.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let pdf = 1.0 / self.surface_area();
+        let side = self.side_area() * pdf;
+        let phi = samples.1 * self.phi_max;
+        if samples.0 < side {
+            let z = self.z_min + samples.0 / side * (self.z_max - self.z_min);
+            let n = Vec3::new(phi.cos(), phi.sin(), 0.0);
+            return ShapeSample {
+                p: Vec3::new(self.r * n.x, self.r * n.y, z),
+                n,
+                pdf,
+            };
+        }
+        // Remap the rest of the sample onto the two caps.
+        let u = ((samples.0 - side) / (1.0 - side)).clamp(0.0, 1.0);
+        let (u, z, n) = if u < 0.5 {
+            (2.0 * u, self.z_min, Vec3::new(0.0, 0.0, -1.0))
+        } else {
+            (2.0 * u - 1.0, self.z_max, Vec3::new(0.0, 0.0, 1.0))
+        };
+        let rho = self.r * u.sqrt();
+        ShapeSample {
+            p: Vec3::new(rho * phi.cos(), rho * phi.sin(), z),
+            n,
+            pdf,
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.side_area() + 2.0 * self.cap_area()
+    }
+}
+
+impl Boundable for Cylinder {
+    fn bounds(&self) -> AABB {
+        AABB::new(
+            Vec3::new(-self.r, -self.r, self.z_min),
+            Vec3::new(self.r, self.r, self.z_max),
+        )
+    }
+}
+
+impl Geometry for Cylinder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cylinder_intersects_side_caps_and_sweep() {
+        let cylinder = Cylinder::new(1.0, -1.0, 1.0);
+        let ray = Ray::new(Vec3::new(3.0, 0.0, 0.5), Vec3::new(-1.0, 0.0, 0.0));
+        let hit = cylinder.intersect_local(&ray).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(1.0, 0.0, 0.0));
+        assert!((hit.uv.1 - 0.75).abs() < 1e-12);
+
+        // Without caps, a ray along the axis escapes through the open ends.
+        let down = Ray::new(Vec3::new(0.5, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(cylinder.intersect_local(&down).is_none());
+        let capped = Cylinder::new(1.0, -1.0, 1.0).with_caps();
+        let hit = capped.intersect_local(&down).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+
+        // A half cylinder is only hit on the side of positive y.
+        let half = Cylinder::new(1.0, -1.0, 1.0).with_phi_max(PI);
+        let ray = Ray::new(Vec3::new(0.0, -3.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = half.intersect_local(&ray).unwrap();
+        assert!((hit.p.y - 1.0).abs() < 1e-12);
+
+        // Samples land on the surface, on the caps as often as their area.
+        let mut on_caps = 0;
+        for i in 0..100 {
+            let sample = capped.sample_uniform(&((i as f64 + 0.5) / 100.0, 0.3));
+            assert!((sample.pdf * capped.surface_area() - 1.0).abs() < 1e-12);
+            if sample.n.z != 0.0 {
+                on_caps += 1;
+                assert!(sample.p.z.abs() == 1.0);
+            } else {
+                assert!((sample.p.x.hypot(sample.p.y) - 1.0).abs() < 1e-12);
+            }
+        }
+        assert_eq!(on_caps, 33);
+    }
+}
@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::vec3::Vec3,
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// A canonical disk of radius `r` in the `z = 0` plane facing `+z`, with a
+/// hole of radius `inner_r` in its middle.
+pub struct Disk {
+    r: f64,
+    inner_r: f64,
+}
+
+impl Disk {
+    pub fn new(r: f64) -> Self {
+        assert!(r > 0.0, "Disk radius must be positive");
+        Self { r, inner_r: 0.0 }
+    }
+
+    /// Make an annulus by cutting a hole of radius `inner_r` in the disk.
+    pub fn with_inner_radius(mut self, inner_r: f64) -> Self {
+        assert!(
+            (0.0..self.r).contains(&inner_r),
+            "Inner radius must be within the disk"
+        );
+        self.inner_r = inner_r;
+        self
+    }
+}
+
+impl Shape for Disk {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        if ray.d.z == 0.0 {
+            return None;
+        }
+        let t = -ray.p.z / ray.d.z;
+        if t <= f64::EPSILON {
+            return None;
+        }
+        let mut p = ray.at(&t);
+        p.z = 0.0;
+        let rho = (p.x * p.x + p.y * p.y).sqrt();
+        if rho > self.r || rho < self.inner_r {
+            return None;
+        }
+        let mut phi = p.y.atan2(p.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        Some(LocalHitRecord {
+            t,
+            p,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (phi / (2.0 * PI), (self.r - rho) / (self.r - self.inner_r)),
+            material_index: 0,
+        })
+    }
+}
+
+impl Sampleable for Disk {
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let rho = (self.inner_r * self.inner_r
+            + samples.0 * (self.r * self.r - self.inner_r * self.inner_r))
+            .sqrt();
+        let phi = 2.0 * PI * samples.1;
+        ShapeSample {
+            p: Vec3::new(rho * phi.cos(), rho * phi.sin(), 0.0),
+            n: Vec3::new(0.0, 0.0, 1.0),
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        PI * (self.r * self.r - self.inner_r * self.inner_r)
+    }
+}
+
+impl Boundable for Disk {
+    fn bounds(&self) -> AABB {
+        AABB::new(
+            Vec3::new(-self.r, -self.r, 0.0),
+            Vec3::new(self.r, self.r, 0.0),
+        )
+    }
+}
+
+impl Geometry for Disk {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_intersection_normal_and_uv() {
+        let disk = Disk::new(2.0).with_inner_radius(0.5);
+        assert!((disk.surface_area() - PI * 3.75).abs() < 1e-12);
+
+        let ray = Ray::new(Vec3::new(1.0, 1.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = disk.intersect_local(&ray).unwrap();
+        assert!((hit.t - 3.0).abs() < 1e-12);
+        assert_eq!(hit.p, Vec3::new(1.0, 1.0, 0.0));
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+        assert!((hit.uv.0 - 0.125).abs() < 1e-12);
+        assert!((hit.uv.1 - (2.0 - 2.0_f64.sqrt()) / 1.5).abs() < 1e-12);
+
+        // Hit from below, the normal does not flip.
+        let ray = Ray::new(Vec3::new(0.0, -1.0, -1.0), Vec3::new(0.0, 0.0, 2.0));
+        let hit = disk.intersect_local(&ray).unwrap();
+        assert!((hit.t - 0.5).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+        assert!((hit.uv.0 - 0.75).abs() < 1e-12);
+
+        // Through the hole, outside the rim, parallel and behind.
+        for ray in [
+            Ray::new(Vec3::new(0.2, 0.3, 1.0), Vec3::new(0.0, 0.0, -1.0)),
+            Ray::new(Vec3::new(1.5, 1.5, 1.0), Vec3::new(0.0, 0.0, -1.0)),
+            Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            Ray::new(Vec3::new(1.0, 0.0, 1.0), Vec3::new(0.0, 0.0, 1.0)),
+        ] {
+            assert!(disk.intersect_local(&ray).is_none());
+        }
+    }
+
+    #[test]
+    fn test_disk_sampling_pdf() {
+        let disk = Disk::new(2.0).with_inner_radius(0.5);
+        let p = Vec3::new(0.5, -1.0, 2.0);
+        let n = 1000;
+        let mut inside = 0;
+        for i in 0..n {
+            let u = (
+                (i as f64 + 0.5) / n as f64,
+                (i as f64 * 0.618_033_988_75).fract(),
+            );
+            let sample = disk.sample_uniform(&u);
+            let rho = sample.p.length();
+            assert!(sample.p.z == 0.0 && (0.5..=2.0).contains(&rho));
+            assert!((sample.pdf - 1.0 / disk.surface_area()).abs() < 1e-12);
+            if rho < 1.25 {
+                inside += 1;
+            }
+
+            // The solid angle density of a sample is the one `pdf` gives
+            // for the direction towards it.
+            let sample = disk.sample(&p, &u);
+            let wi = (sample.p - p).normalize();
+            assert!((sample.pdf - disk.pdf(&p, &wi)).abs() < 1e-9 * sample.pdf);
+        }
+        // Samples are uniform in area.
+        let expected = (1.25 * 1.25 - 0.25) / 3.75;
+        assert!((inside as f64 / n as f64 - expected).abs() < 0.01);
+    }
+}
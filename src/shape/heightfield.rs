@@ -0,0 +1,374 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quadratic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// A terrain over the unit square of the `z = 0` plane, with the elevations
+/// of an `nx` by `ny` grid of samples spread over it, facing `+z`. Each cell
+/// between four samples is a bilinear patch, so the terrain is smooth
+/// without ever being expanded into triangles.
+///
+/// Rays walk the cells they cross with a 2D DDA, skipping the cells whose
+/// elevations they pass above or below, and intersect the patches
+/// analytically.
+///
+/// The area of the patches has no closed form, so unlike other shapes the
+/// terrain is not sampled uniformly by area: `sample_uniform` samples the
+/// unit square below it, and `pdf_area` gives the resulting density.
+pub struct Heightfield {
+    nx: usize,
+    ny: usize,
+    /// Elevations row by row from `y = 0`. Single precision keeps terrains
+    /// of millions of samples small.
+    heights: Vec<f32>,
+    bounds: AABB,
+    /// Area of the terrain, approximated by two triangles per cell. Only
+    /// used as a measure of its size, samples are not drawn by area.
+    area: f64,
+}
+
+impl Heightfield {
+    pub fn new(nx: usize, ny: usize, heights: Vec<f32>) -> Self {
+        assert!(
+            nx >= 2 && ny >= 2,
+            "A heightfield needs at least 2 by 2 samples"
+        );
+        assert_eq!(
+            heights.len(),
+            nx * ny,
+            "One height per grid sample expected"
+        );
+        let (min, max) = heights
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &h| {
+                (min.min(h as f64), max.max(h as f64))
+            });
+        let mut heightfield = Self {
+            nx,
+            ny,
+            heights,
+            bounds: AABB::new(Vec3::new(0.0, 0.0, min), Vec3::new(1.0, 1.0, max)),
+            area: 0.0,
+        };
+        heightfield.area = heightfield.triangulated_area();
+        heightfield
+    }
+
+    /// Load the elevations from the brightness of a PGM or PPM image, in
+    /// ASCII or binary form, between 0 for black and 1 for white. The top of
+    /// the image is at `y = 1`.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {message}", path.display()),
+            )
+        };
+        let data = fs::read(path)?;
+        let mut pos = 0;
+        let mut header = Vec::with_capacity(4);
+        while header.len() < 4 {
+            // Skip whitespace and comments.
+            while pos < data.len() && (data[pos].is_ascii_whitespace() || data[pos] == b'#') {
+                if data[pos] == b'#' {
+                    while pos < data.len() && data[pos] != b'\n' {
+                        pos += 1;
+                    }
+                } else {
+                    pos += 1;
+                }
+            }
+            let start = pos;
+            while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if start == pos {
+                return Err(invalid("Truncated header"));
+            }
+            header.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+        }
+
+        let (channels, binary) = match header[0].as_str() {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            magic => return Err(invalid(&format!("Unsupported format {magic}"))),
+        };
+        let parse = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| invalid(&format!("Invalid number {s}")))
+        };
+        let (width, height, max_value) =
+            (parse(&header[1])?, parse(&header[2])?, parse(&header[3])?);
+        if width < 2 || height < 2 || max_value == 0 || max_value > 65535 {
+            return Err(invalid("Invalid dimensions or maximum value"));
+        }
+
+        let n = width * height * channels;
+        let values: Vec<usize> = if binary {
+            // A single whitespace byte separates the header from the data.
+            let bytes = data.get(pos + 1..).unwrap_or_default();
+            let size = if max_value < 256 { 1 } else { 2 };
+            if bytes.len() < n * size {
+                return Err(invalid("Truncated data"));
+            }
+            bytes
+                .chunks_exact(size)
+                .take(n)
+                .map(|c| c.iter().fold(0, |value, &byte| value << 8 | byte as usize))
+                .collect()
+        } else {
+            let values = String::from_utf8_lossy(&data[pos..])
+                .split_ascii_whitespace()
+                .take(n)
+                .map(parse)
+                .collect::<io::Result<Vec<_>>>()?;
+            if values.len() < n {
+                return Err(invalid("Truncated data"));
+            }
+            values
+        };
+
+        let scale = 1.0 / (channels * max_value) as f32;
+        let mut heights = vec![0.0; width * height];
+        for (i, pixel) in values.chunks_exact(channels).enumerate() {
+            let (x, row) = (i % width, i / width);
+            heights[(height - 1 - row) * width + x] = pixel.iter().sum::<usize>() as f32 * scale;
+        }
+        Ok(Self::new(width, height, heights))
+    }
+
+    fn height(&self, x: usize, y: usize) -> f64 {
+        self.heights[y * self.nx + x] as f64
+    }
+
+    /// Size of a cell along x and y.
+    fn cell_size(&self) -> (f64, f64) {
+        (1.0 / (self.nx - 1) as f64, 1.0 / (self.ny - 1) as f64)
+    }
+
+    /// Elevations at the corners of cell `(i, j)`, as `[z00, z10, z01, z11]`.
+    fn corners(&self, i: usize, j: usize) -> [f64; 4] {
+        [
+            self.height(i, j),
+            self.height(i + 1, j),
+            self.height(i, j + 1),
+            self.height(i + 1, j + 1),
+        ]
+    }
+
+    /// The point above `(x, y)` on the terrain and its normal.
+    fn surface(&self, x: f64, y: f64) -> (Vec3, Vec3) {
+        let (dx, dy) = self.cell_size();
+        let i = ((x / dx) as usize).min(self.nx - 2);
+        let j = ((y / dy) as usize).min(self.ny - 2);
+        let (u, v) = (x / dx - i as f64, y / dy - j as f64);
+        let [z00, z10, z01, z11] = self.corners(i, j);
+        let z = (1.0 - v) * ((1.0 - u) * z00 + u * z10) + v * ((1.0 - u) * z01 + u * z11);
+        let dz_dx = ((1.0 - v) * (z10 - z00) + v * (z11 - z01)) / dx;
+        let dz_dy = ((1.0 - u) * (z01 - z00) + u * (z11 - z10)) / dy;
+        (
+            Vec3::new(x, y, z),
+            Vec3::new(-dz_dx, -dz_dy, 1.0).normalize(),
+        )
+    }
+
+    fn triangulated_area(&self) -> f64 {
+        let (dx, dy) = self.cell_size();
+        let mut area = 0.0;
+        for j in 0..self.ny - 1 {
+            for i in 0..self.nx - 1 {
+                let [z00, z10, z01, z11] = self.corners(i, j);
+                let (p00, p11) = (Vec3::new(0.0, 0.0, z00), Vec3::new(dx, dy, z11));
+                let (p10, p01) = (Vec3::new(dx, 0.0, z10), Vec3::new(0.0, dy, z01));
+                area += 0.5 * (p10 - p00).cross(&(p11 - p00)).length();
+                area += 0.5 * (p11 - p00).cross(&(p01 - p00)).length();
+            }
+        }
+        area
+    }
+
+    /// The first intersection of the ray with the patch of cell `(i, j)`
+    /// between `t_in` and `t_out`. Along the ray the cell coordinates are
+    /// linear in `t`, so the bilinear patch gives a quadratic in `t`.
+    fn intersect_cell(
+        &self,
+        ray: &Ray,
+        (i, j): (usize, usize),
+        t_in: f64,
+        t_out: f64,
+    ) -> Option<f64> {
+        let [z00, z10, z01, z11] = self.corners(i, j);
+        let (z_in, z_out) = (ray.p.z + t_in * ray.d.z, ray.p.z + t_out * ray.d.z);
+        if z_in.min(z_out) > z00.max(z10).max(z01).max(z11)
+            || z_in.max(z_out) < z00.min(z10).min(z01).min(z11)
+        {
+            return None;
+        }
+
+        let (dx, dy) = self.cell_size();
+        let (au, bu) = (ray.p.x / dx - i as f64, ray.d.x / dx);
+        let (av, bv) = (ray.p.y / dy - j as f64, ray.d.y / dy);
+        let (a, b, c) = (z10 - z00, z01 - z00, z00 - z10 - z01 + z11);
+        let (t0, t1) = solve_quadratic(
+            -c * bu * bv,
+            ray.d.z - (a * bu + b * bv + c * (au * bv + bu * av)),
+            ray.p.z - (z00 + a * au + b * av + c * au * av),
+        )?;
+        // Tolerate roots slightly out of the cell so rays cannot slip
+        // between neighbouring patches.
+        let slack = 1e-9 * (t_out - t_in).max(1.0);
+        [t0, t1]
+            .into_iter()
+            .find(|&t| t > f64::EPSILON && t >= t_in - slack && t <= t_out + slack)
+    }
+}
+
+impl Shape for Heightfield {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let (t_enter, t_exit) = self.bounds.intersect(ray, f64::INFINITY)?;
+        let (dx, dy) = self.cell_size();
+        let entry = ray.at(&t_enter);
+        let mut cell = [
+            ((entry.x / dx).max(0.0) as usize).min(self.nx - 2),
+            ((entry.y / dy).max(0.0) as usize).min(self.ny - 2),
+        ];
+        let last = [self.nx - 2, self.ny - 2];
+        let size = [dx, dy];
+
+        // Ray parameter at the next cell boundary along each axis, and
+        // between boundaries.
+        let mut t_next = [f64::INFINITY; 2];
+        let mut t_delta = [f64::INFINITY; 2];
+        for axis in 0..2 {
+            if ray.d[axis] > 0.0 {
+                t_next[axis] = ((cell[axis] + 1) as f64 * size[axis] - ray.p[axis]) / ray.d[axis];
+                t_delta[axis] = size[axis] / ray.d[axis];
+            } else if ray.d[axis] < 0.0 {
+                t_next[axis] = (cell[axis] as f64 * size[axis] - ray.p[axis]) / ray.d[axis];
+                t_delta[axis] = -size[axis] / ray.d[axis];
+            }
+        }
+
+        let mut t_in = t_enter;
+        loop {
+            let axis = if t_next[0] < t_next[1] { 0 } else { 1 };
+            let t_out = t_next[axis].min(t_exit);
+            if let Some(t) = self.intersect_cell(ray, (cell[0], cell[1]), t_in, t_out) {
+                let p = ray.at(&t);
+                let (x, y) = (p.x.clamp(0.0, 1.0), p.y.clamp(0.0, 1.0));
+                let (_, n) = self.surface(x, y);
+                return Some(LocalHitRecord {
+                    t,
+                    p,
+                    n,
+                    ns: n,
+                    tangent: None,
+                    uv: (x, y),
+                    material_index: 0,
+                });
+            }
+            if t_out >= t_exit {
+                return None;
+            }
+            let step_back = ray.d[axis] < 0.0;
+            if (step_back && cell[axis] == 0) || (!step_back && cell[axis] == last[axis]) {
+                return None;
+            }
+            cell[axis] = if step_back {
+                cell[axis] - 1
+            } else {
+                cell[axis] + 1
+            };
+            t_in = t_out;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl Sampleable for Heightfield {
+    /// Sample the point above a uniform position of the unit square. Its
+    /// density with respect to area is the cosine of the slope there.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let (p, n) = self.surface(samples.0, samples.1);
+        ShapeSample { p, n, pdf: n.z }
+    }
+
+    fn pdf_area(&self, p: &Vec3) -> f64 {
+        self.surface(p.x.clamp(0.0, 1.0), p.y.clamp(0.0, 1.0)).1.z
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.area
+    }
+}
+
+impl Boundable for Heightfield {
+    fn bounds(&self) -> AABB {
+        self.bounds
+    }
+}
+
+impl Geometry for Heightfield {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        light::{Light, area::AreaLight},
+        math::transform::Transform,
+        render::colour::Colour,
+    };
+
+    #[test]
+    fn test_heightfield_from_image() {
+        // A 3 by 2 image, brighter to the right and at the top.
+        let path = std::env::temp_dir().join("ray_tracer_test_heightfield.pgm");
+        fs::write(&path, "P2\n# terrain\n3 2\n4\n1 2 3\n0 1 2\n").unwrap();
+        let heightfield = Heightfield::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!((heightfield.nx, heightfield.ny), (3, 2));
+        assert_eq!(heightfield.height(2, 1), 0.75);
+        assert_eq!(heightfield.height(0, 0), 0.0);
+
+        // Straight down onto the bilinear patch of the first cell.
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let hit = heightfield
+            .intersect_local(&Ray::new(Vec3::new(0.25, 0.5, 2.0), down))
+            .unwrap();
+        assert!((hit.p.z - 0.25).abs() < 1e-9);
+        assert!(hit.n.z > 0.0 && hit.n.x < 0.0 && hit.n.y < 0.0);
+
+        // A grazing ray crossing several cells hits where the terrain rises
+        // above it, along `z = 0.125 + 0.5 x`.
+        let ray = Ray::new(Vec3::new(0.0, 0.5, 0.4), Vec3::new(1.0, 0.0, 0.0));
+        let hit = heightfield.intersect_local(&ray).unwrap();
+        assert!((hit.p.x - 0.55).abs() < 1e-9, "{}", hit.p.x);
+        assert!(
+            heightfield
+                .intersect_local(&Ray::new(Vec3::new(0.0, 0.5, 0.7), ray.d))
+                .is_none()
+        );
+
+        // As a light, the density of emitted rays matches the samples.
+        let light = AreaLight::new(
+            Arc::new(heightfield),
+            Transform::new_identity(),
+            Colour::grey(1.0),
+        );
+        for i in 0..10 {
+            let emission = light
+                .sample_le(((i as f64 + 0.5) / 10.0, 0.3), (0.6, 0.2))
+                .unwrap();
+            let (pdf_pos, _) = light.pdf_le(&emission.ray, &emission.n);
+            assert!((pdf_pos - emission.pdf_pos).abs() < 1e-9 * emission.pdf_pos);
+        }
+    }
+}
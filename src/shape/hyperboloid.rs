@@ -0,0 +1,195 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quadratic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample, sweep_phi},
+};
+
+/// Newton iterations inverting the area integral when sampling.
+const SAMPLE_ITERATIONS: usize = 32;
+
+/// A canonical hyperboloid of one sheet `x^2 + y^2 = r^2 (1 + z^2 / c^2)`
+/// around the z axis, with its waist of radius `r` at `z = 0`, cut to
+/// `z_min <= z <= z_max` and swept from `phi = 0` to `phi_max`. The larger
+/// `c`, the straighter its sides; cooling towers are cut asymmetrically.
+pub struct Hyperboloid {
+    r: f64,
+    c: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+}
+
+impl Hyperboloid {
+    pub fn new(r: f64, c: f64, z_min: f64, z_max: f64) -> Self {
+        assert!(
+            r > 0.0 && c > 0.0,
+            "Hyperboloid waist radius and c must be positive"
+        );
+        Self {
+            r,
+            c,
+            z_min: z_min.min(z_max),
+            z_max: z_min.max(z_max),
+            phi_max: 2.0 * PI,
+        }
+    }
+
+    /// Only sweep the hyperboloid up to the angle `phi_max`, in radians.
+    pub fn with_phi_max(mut self, phi_max: f64) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// The factor `m` of `x^2 + y^2 = r^2 + m z^2`.
+    fn m(&self) -> f64 {
+        (self.r / self.c).powi(2)
+    }
+
+    fn radius_at(&self, z: f64) -> f64 {
+        (self.r * self.r + self.m() * z * z).sqrt()
+    }
+
+    /// Outward normal at `p`, the gradient of `x^2 + y^2 - m z^2 - r^2`.
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(p.x, p.y, -self.m() * p.z).normalize()
+    }
+
+    /// The area element along z, `rho sqrt(1 + rho'^2) = sqrt(r^2 + q z^2)`
+    /// with `q = m (1 + m)`, per unit of phi.
+    fn area_element(&self, z: f64) -> f64 {
+        let m = self.m();
+        (self.r * self.r + m * (1.0 + m) * z * z).sqrt()
+    }
+
+    /// Antiderivative of `area_element`.
+    fn area_integral(&self, z: f64) -> f64 {
+        let m = self.m();
+        let sqrt_q = (m * (1.0 + m)).sqrt();
+        0.5 * (z * self.area_element(z) + self.r * self.r / sqrt_q * (z * sqrt_q / self.r).asinh())
+    }
+}
+
+impl Shape for Hyperboloid {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let m = self.m();
+        let (o, d) = (ray.p, ray.d);
+        let a = d.x * d.x + d.y * d.y - m * d.z * d.z;
+        let b = 2.0 * (d.x * o.x + d.y * o.y - m * d.z * o.z);
+        let c = o.x * o.x + o.y * o.y - m * o.z * o.z - self.r * self.r;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > f64::EPSILON)
+            .find_map(|t| {
+                let p = ray.at(&t);
+                if p.z < self.z_min || p.z > self.z_max {
+                    return None;
+                }
+                let phi = sweep_phi(&p, self.phi_max)?;
+                let n = self.normal(&p);
+                Some(LocalHitRecord {
+                    t,
+                    p,
+                    n,
+                    ns: n,
+                    tangent: None,
+                    uv: (
+                        phi / self.phi_max,
+                        (p.z - self.z_min) / (self.z_max - self.z_min),
+                    ),
+                    material_index: 0,
+                })
+            })
+    }
+}
+
+impl Sampleable for Hyperboloid {
+    /// Invert the area integral along z by safeguarded Newton iterations,
+    /// then sample phi uniformly.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let a0 = self.area_integral(self.z_min);
+        let target = a0 + samples.0 * (self.area_integral(self.z_max) - a0);
+        let (mut lo, mut hi) = (self.z_min, self.z_max);
+        let mut z = self.z_min + samples.0 * (self.z_max - self.z_min);
+        for _ in 0..SAMPLE_ITERATIONS {
+            let f = self.area_integral(z) - target;
+            if f.abs() < 1e-12 {
+                break;
+            }
+            if f < 0.0 {
+                lo = z;
+            } else {
+                hi = z;
+            }
+            z -= f / self.area_element(z);
+            // Bisect when Newton leaves the bracket.
+            if !(lo..=hi).contains(&z) {
+                z = 0.5 * (lo + hi);
+            }
+        }
+        let rho = self.radius_at(z);
+        let phi = samples.1 * self.phi_max;
+        let p = Vec3::new(rho * phi.cos(), rho * phi.sin(), z);
+        ShapeSample {
+            p,
+            n: self.normal(&p),
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.phi_max * (self.area_integral(self.z_max) - self.area_integral(self.z_min))
+    }
+}
+
+impl Boundable for Hyperboloid {
+    fn bounds(&self) -> AABB {
+        let rho = self.radius_at(self.z_min).max(self.radius_at(self.z_max));
+        AABB::new(
+            Vec3::new(-rho, -rho, self.z_min),
+            Vec3::new(rho, rho, self.z_max),
+        )
+    }
+}
+
+impl Geometry for Hyperboloid {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hyperboloid_area_and_samples() {
+        let hyperboloid = Hyperboloid::new(1.0, 0.8, -1.0, 2.0).with_phi_max(1.5 * PI);
+
+        // Sum the areas of thin conical frustums.
+        let n = 10000;
+        let dz = 3.0 / n as f64;
+        let area: f64 = (0..n)
+            .map(|i| {
+                let (z0, z1) = (-1.0 + i as f64 * dz, -1.0 + (i + 1) as f64 * dz);
+                let (r0, r1) = (hyperboloid.radius_at(z0), hyperboloid.radius_at(z1));
+                0.5 * (r0 + r1) * (dz * dz + (r1 - r0).powi(2)).sqrt()
+            })
+            .sum::<f64>()
+            * 1.5
+            * PI;
+        assert!((hyperboloid.surface_area() - area).abs() < 1e-6 * area);
+
+        // Half of the samples land below the z splitting the area in half.
+        let half = hyperboloid.sample_uniform(&(0.5, 0.0)).p.z;
+        let below = (hyperboloid.area_integral(half) - hyperboloid.area_integral(-1.0)) * 1.5 * PI;
+        assert!((below - 0.5 * area).abs() < 1e-6 * area);
+
+        // Samples lie on the surface.
+        for i in 0..10 {
+            let sample = hyperboloid.sample_uniform(&(i as f64 / 10.0, 0.7));
+            let ray = Ray::new(sample.p + sample.n, -sample.n);
+            let hit = hyperboloid.intersect_local(&ray).unwrap();
+            assert!((hit.p - sample.p).length() < 1e-9);
+        }
+    }
+}
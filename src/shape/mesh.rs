@@ -0,0 +1,535 @@
+use std::sync::Arc;
+
+use crate::{
+    accel::aabb::AABB,
+    math::vec3::Vec3,
+    render::ray::Ray,
+    shape::{
+        Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample,
+        triangle::{
+            interpolate_point, interpolate_uv, intersect_triangle, sample_barycentrics,
+            triangle_area, triangle_normals,
+        },
+    },
+};
+
+/// Triangles sharing vertex buffers. Every face holds three indices into
+/// the vertex attributes, and optionally the index of its material among
+/// the materials of the primitive the mesh belongs to.
+///
+/// The whole mesh is a single shape, so it takes a single `Primitive`. Its
+/// faces can also be handed out one by one as `MeshTriangle`s, which only
+/// hold a reference to the mesh and a face index. The vertex buffers are
+/// shared with the parts of the mesh split off by material.
+///
+/// Rays are intersected with the faces through a bounding volume hierarchy
+/// built over them with the mesh.
+pub struct TriangleMesh {
+    positions: Arc<Vec<Vec3>>,
+    normals: Option<Arc<Vec<Vec3>>>,
+    uvs: Option<Arc<Vec<(f64, f64)>>>,
+    tangents: Option<Arc<Vec<Vec3>>>,
+    indices: Vec<[usize; 3]>,
+    material_indices: Option<Vec<usize>>,
+    /// Running sum of the areas of the faces, to sample them by area.
+    area_cdf: Vec<f64>,
+    /// Bounding volume hierarchy over the faces, whose leaves hold ranges
+    /// of `face_order`.
+    nodes: Vec<FaceNode>,
+    face_order: Vec<usize>,
+}
+
+/// Maximum number of faces in a leaf of the hierarchy of a mesh.
+const MAX_FACES_IN_NODE: usize = 4;
+
+/// A node of the hierarchy of a mesh. Nodes are stored depth first, so an
+/// interior node is directly followed by its first child.
+struct FaceNode {
+    bounds: AABB,
+    /// For a leaf, the start of its faces in `face_order`, otherwise the
+    /// index of its second child.
+    offset: usize,
+    /// Number of faces of a leaf, 0 for interior nodes.
+    n_faces: usize,
+}
+
+impl TriangleMesh {
+    /// A mesh with the faces `indices` over the vertices `positions`.
+    pub fn new(positions: Vec<Vec3>, indices: Vec<[usize; 3]>) -> Self {
+        assert!(
+            indices.iter().flatten().all(|&i| i < positions.len()),
+            "Vertex index out of bounds"
+        );
+        Self {
+            positions: Arc::new(positions),
+            normals: None,
+            uvs: None,
+            tangents: None,
+            indices,
+            material_indices: None,
+            area_cdf: Vec::new(),
+            nodes: Vec::new(),
+            face_order: Vec::new(),
+        }
+        .index_faces()
+    }
+
+    /// Compute the area distribution and the hierarchy of the faces.
+    fn index_faces(mut self) -> Self {
+        let mut total = 0.0;
+        self.area_cdf = (0..self.n_faces())
+            .map(|face| {
+                total += triangle_area(&self.vertices(face));
+                total
+            })
+            .collect();
+        let face_bounds: Vec<AABB> = (0..self.n_faces())
+            .map(|face| self.face_bounds(face))
+            .collect();
+        self.face_order = (0..self.n_faces()).collect();
+        self.nodes.clear();
+        if !self.face_order.is_empty() {
+            build_nodes(&mut self.nodes, &face_bounds, &mut self.face_order, 0);
+        }
+        self
+    }
+
+    /// Per-vertex shading normals.
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(
+            normals.len(),
+            self.positions.len(),
+            "One normal per vertex expected"
+        );
+        self.normals = Some(Arc::new(normals.iter().map(|n| n.normalize()).collect()));
+        self
+    }
+
+    /// Per-vertex texture coordinates.
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        assert_eq!(
+            uvs.len(),
+            self.positions.len(),
+            "One uv per vertex expected"
+        );
+        self.uvs = Some(Arc::new(uvs));
+        self
+    }
+
+    /// Per-vertex tangents.
+    pub fn with_tangents(mut self, tangents: Vec<Vec3>) -> Self {
+        assert_eq!(
+            tangents.len(),
+            self.positions.len(),
+            "One tangent per vertex expected"
+        );
+        self.tangents = Some(Arc::new(tangents));
+        self
+    }
+
+    /// Per-face material indices.
+    pub fn with_material_indices(mut self, material_indices: Vec<usize>) -> Self {
+        assert_eq!(
+            material_indices.len(),
+            self.indices.len(),
+            "One material index per face expected"
+        );
+        self.material_indices = Some(material_indices);
+        self
+    }
+
+    pub fn n_faces(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Lightweight references to every face of the mesh.
+    pub fn triangles(self: &Arc<Self>) -> Vec<MeshTriangle> {
+        (0..self.n_faces())
+            .map(|face| MeshTriangle {
+                mesh: self.clone(),
+                face,
+            })
+            .collect()
+    }
+
+    fn material_index(&self, face: usize) -> usize {
+        self.material_indices
+            .as_ref()
+            .map_or(0, |indices| indices[face])
+    }
+
+    fn vertices(&self, face: usize) -> [Vec3; 3] {
+        self.indices[face].map(|i| self.positions[i])
+    }
+
+    /// The tangent of `face` at the barycentric coordinates `b`, if the mesh
+    /// has tangents.
+    fn tangent(&self, face: usize, b: &[f64; 3]) -> Option<Vec3> {
+        let tangents = self.tangents.as_ref()?;
+        Some(interpolate_point(
+            &self.indices[face].map(|i| tangents[i]),
+            b,
+        ))
+    }
+
+    fn face_area(&self, face: usize) -> f64 {
+        self.area_cdf[face]
+            - if face > 0 {
+                self.area_cdf[face - 1]
+            } else {
+                0.0
+            }
+    }
+
+    fn intersect_face(&self, face: usize, ray: &Ray) -> Option<LocalHitRecord> {
+        let p = self.vertices(face);
+        let (t, b) = intersect_triangle(&p, ray)?;
+        let indices = self.indices[face];
+        let normals = self
+            .normals
+            .as_ref()
+            .map(|normals| indices.map(|i| normals[i]));
+        let (n, ns) = triangle_normals(&p, normals, &b);
+        let uv = match &self.uvs {
+            Some(uvs) => interpolate_uv(&indices.map(|i| uvs[i]), &b),
+            None => (b[1] + b[2], b[2]),
+        };
+        Some(LocalHitRecord {
+            t,
+            p: interpolate_point(&p, &b),
+            n,
+            ns,
+            tangent: self.tangent(face, &b),
+            uv,
+            material_index: self.material_index(face),
+        })
+    }
+
+    fn sample_face(&self, face: usize, samples: (f64, f64)) -> ShapeSample {
+        let p = self.vertices(face);
+        let b = sample_barycentrics(samples);
+        let normals = self
+            .normals
+            .as_ref()
+            .map(|normals| self.indices[face].map(|i| normals[i]));
+        ShapeSample {
+            p: interpolate_point(&p, &b),
+            n: triangle_normals(&p, normals, &b).0,
+            pdf: 1.0 / self.face_area(face),
+        }
+    }
+
+    fn face_bounds(&self, face: usize) -> AABB {
+        let [p0, p1, p2] = self.vertices(face);
+        AABB::new(p0, p1).union_point(&p2)
+    }
+}
+
+impl Shape for TriangleMesh {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let mut closest: Option<LocalHitRecord> = None;
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let t_max = closest.as_ref().map_or(f64::INFINITY, |hit| hit.t);
+            if node.bounds.intersect(ray, t_max).is_none() {
+                continue;
+            }
+            if node.n_faces == 0 {
+                stack.extend([node.offset, index + 1]);
+                continue;
+            }
+            for &face in &self.face_order[node.offset..node.offset + node.n_faces] {
+                if let Some(hit) = self.intersect_face(face, ray)
+                    && closest.as_ref().is_none_or(|closest| hit.t < closest.t)
+                {
+                    closest = Some(hit);
+                }
+            }
+        }
+        closest
+    }
+}
+
+impl Sampleable for TriangleMesh {
+    /// Pick a face proportionally to its area, then a point uniformly on it.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let area = self.surface_area();
+        let target = samples.0 * area;
+        let face = self
+            .area_cdf
+            .partition_point(|&c| c <= target)
+            .min(self.n_faces().saturating_sub(1));
+        let start = if face > 0 {
+            self.area_cdf[face - 1]
+        } else {
+            0.0
+        };
+        let u = ((target - start) / self.face_area(face)).clamp(0.0, 1.0);
+        let mut sample = self.sample_face(face, (u, samples.1));
+        sample.pdf = 1.0 / area;
+        sample
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.area_cdf.last().copied().unwrap_or(0.0)
+    }
+}
+
+impl Boundable for TriangleMesh {
+    fn bounds(&self) -> AABB {
+        self.positions
+            .iter()
+            .fold(AABB::empty(), |acc, p| acc.union_point(p))
+    }
+}
+
+impl Geometry for TriangleMesh {
+    /// The faces whose material index is accepted by `filter`, sharing the
+    /// vertex buffers of the mesh.
+    fn material_part(&self, filter: &dyn Fn(usize) -> bool) -> Option<Arc<dyn Geometry>> {
+        let faces: Vec<usize> = (0..self.n_faces())
+            .filter(|&face| filter(self.material_index(face)))
+            .collect();
+        let part = Self {
+            positions: self.positions.clone(),
+            normals: self.normals.clone(),
+            uvs: self.uvs.clone(),
+            tangents: self.tangents.clone(),
+            indices: faces.iter().map(|&face| self.indices[face]).collect(),
+            material_indices: self
+                .material_indices
+                .as_ref()
+                .map(|material_indices| faces.iter().map(|&face| material_indices[face]).collect()),
+            area_cdf: Vec::new(),
+            nodes: Vec::new(),
+            face_order: Vec::new(),
+        };
+        Some(Arc::new(part.index_faces()))
+    }
+}
+
+/// Build the subtree of a mesh hierarchy over `faces`, which start at
+/// `offset` in the face order, splitting them at the median of their
+/// centroids along the axis the centroids spread the most over.
+fn build_nodes(
+    nodes: &mut Vec<FaceNode>,
+    face_bounds: &[AABB],
+    faces: &mut [usize],
+    offset: usize,
+) {
+    let bounds = faces
+        .iter()
+        .fold(AABB::empty(), |acc, &face| acc.union(&face_bounds[face]));
+    let centroid_bounds = faces.iter().fold(AABB::empty(), |acc, &face| {
+        acc.union_point(&face_bounds[face].center())
+    });
+    let extent = centroid_bounds.diagonal();
+    let axis = (0..3)
+        .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+        .unwrap_or(0);
+    if faces.len() <= MAX_FACES_IN_NODE || extent[axis] == 0.0 {
+        nodes.push(FaceNode {
+            bounds,
+            offset,
+            n_faces: faces.len(),
+        });
+        return;
+    }
+
+    let node = nodes.len();
+    nodes.push(FaceNode {
+        bounds,
+        offset: 0,
+        n_faces: 0,
+    });
+    let mid = faces.len() / 2;
+    faces.select_nth_unstable_by(mid, |&a, &b| {
+        face_bounds[a].center()[axis].total_cmp(&face_bounds[b].center()[axis])
+    });
+    let (left, right) = faces.split_at_mut(mid);
+    build_nodes(nodes, face_bounds, left, offset);
+    nodes[node].offset = nodes.len();
+    build_nodes(nodes, face_bounds, right, offset + mid);
+}
+
+/// A single face of a `TriangleMesh`.
+#[derive(Clone)]
+pub struct MeshTriangle {
+    mesh: Arc<TriangleMesh>,
+    face: usize,
+}
+
+impl MeshTriangle {
+    pub fn face(&self) -> usize {
+        self.face
+    }
+}
+
+impl Shape for MeshTriangle {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        self.mesh.intersect_face(self.face, ray)
+    }
+}
+
+impl Sampleable for MeshTriangle {
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        self.mesh.sample_face(self.face, *samples)
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.mesh.face_area(self.face)
+    }
+}
+
+impl Boundable for MeshTriangle {
+    fn bounds(&self) -> AABB {
+        self.mesh.face_bounds(self.face)
+    }
+}
+
+impl Geometry for MeshTriangle {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        material::Material, math::transform::Transform, render::colour::Colour, scene::Scene,
+        shape::primitive::Primitive,
+    };
+
+    fn unit_square() -> TriangleMesh {
+        // A unit square in the xy plane split along its diagonal.
+        TriangleMesh::new(
+            vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 1.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+        .with_material_indices(vec![0, 1])
+    }
+
+    #[test]
+    fn test_mesh_faces_pick_their_material() {
+        let mesh = unit_square();
+        assert_eq!(mesh.surface_area(), 1.0);
+
+        let red = Arc::new(Material::diffuse(Colour::rgb(1.0, 0.0, 0.0)));
+        let blue = Arc::new(Material::diffuse(Colour::rgb(0.0, 0.0, 1.0)));
+        let primitive = Primitive::new(Arc::new(mesh), Transform::new_identity(), red.clone())
+            .with_materials(vec![red.clone(), blue.clone()]);
+
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let hit = primitive
+            .intersect(&Ray::new(Vec3::new(0.75, 0.25, 1.0), down))
+            .unwrap();
+        assert!(std::ptr::eq(hit.material, red.as_ref()));
+        let hit = primitive
+            .intersect(&Ray::new(Vec3::new(0.25, 0.75, 1.0), down))
+            .unwrap();
+        assert!(std::ptr::eq(hit.material, blue.as_ref()));
+        assert!(
+            primitive
+                .intersect(&Ray::new(Vec3::new(1.5, 0.5, 1.0), down))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_emissive_faces_are_lights_of_their_own() {
+        let red = Arc::new(Material::diffuse(Colour::rgb(1.0, 0.0, 0.0)));
+        let light = Arc::new(Material::light(Colour::grey(1.0)));
+        let primitive = Primitive::new(
+            Arc::new(unit_square()),
+            Transform::new_identity(),
+            red.clone(),
+        )
+        .with_materials(vec![red.clone(), light.clone()]);
+        let mut scene = Scene::new();
+        scene.add_primitive(primitive);
+        assert_eq!(scene.lights().len(), 1);
+
+        // Only the emissive face is sampled, and only its hits are on the
+        // light.
+        let p = Vec3::new(0.5, 0.5, 1.0);
+        for i in 0..100 {
+            let sample = scene.lights()[0]
+                .sample_li(&p, ((i as f64 + 0.5) / 100.0, 0.3))
+                .unwrap();
+            assert!(sample.p.y >= sample.p.x);
+            let d = sample.p - p;
+            assert!((sample.pdf * 0.5 * sample.wi.z.abs() / d.length_squared() - 1.0).abs() < 1e-9);
+        }
+        let primitive = &scene.primitives()[0];
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        assert!(
+            primitive
+                .intersect(&Ray::new(Vec3::new(0.75, 0.25, 1.0), down))
+                .unwrap()
+                .area_light
+                .is_none()
+        );
+        assert!(
+            primitive
+                .intersect(&Ray::new(Vec3::new(0.25, 0.75, 1.0), down))
+                .unwrap()
+                .area_light
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_hierarchy_finds_the_closest_face() {
+        // A grid of bumpy quads, hit by rays from random points.
+        let n = 20;
+        let mut positions = Vec::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                let (x, y) = (i as f64 / n as f64, j as f64 / n as f64);
+                positions.push(Vec3::new(x, y, 0.1 * (7.0 * x).sin() * (5.0 * y).cos()));
+            }
+        }
+        let mut indices = Vec::new();
+        for j in 0..n {
+            for i in 0..n {
+                let v = j * (n + 1) + i;
+                indices.push([v, v + 1, v + n + 2]);
+                indices.push([v, v + n + 2, v + n + 1]);
+            }
+        }
+        let tangents = vec![Vec3::new(1.0, 0.0, 0.0); positions.len()];
+        let mesh = TriangleMesh::new(positions, indices).with_tangents(tangents);
+        assert!(mesh.nodes.len() > 1);
+
+        let mut state = 1u64;
+        let mut random = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        };
+        for _ in 0..1000 {
+            let p = Vec3::new(
+                2.0 * random() - 0.5,
+                2.0 * random() - 0.5,
+                2.0 * random() - 1.0,
+            );
+            let target = Vec3::new(random(), random(), 0.0);
+            let ray = Ray::new(p, target - p);
+            let expected = (0..mesh.n_faces())
+                .filter_map(|face| mesh.intersect_face(face, &ray))
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            let hit = mesh.intersect_local(&ray);
+            assert_eq!(hit.as_ref().map(|hit| hit.t), expected.map(|hit| hit.t));
+            assert!(hit.is_none_or(
+                |hit| (hit.tangent.unwrap() - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-12
+            ));
+        }
+    }
+}
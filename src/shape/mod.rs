@@ -1,48 +1,169 @@
+pub mod cone;
+pub mod csg;
 pub mod cube;
 pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
+pub mod hyperboloid;
+pub mod mesh;
+pub mod paraboloid;
+pub mod plane;
 pub mod primitive;
+pub mod quad;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
 pub mod triangle;
 
+use std::sync::Arc;
+
 use primitive::Primitive;
 
-use crate::{accel::aabb::AABB, math::vec3::Vec3, render::ray::Ray};
+use crate::{
+    accel::aabb::AABB, light::area::AreaLight, material::Material, math::vec3::Vec3,
+    render::ray::Ray,
+};
 
 pub trait Shape {
     /// Test ray-shape intersection in object space
     fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord>;
+
+    /// Every intersection of the ray with the shape in object space, in
+    /// increasing `t`. On a closed shape with outward normals, the hits where
+    /// the normal faces the ray enter the shape and the others leave it.
+    /// The default follows the ray from one hit to the next.
+    fn intersect_all_local(&self, ray: &Ray) -> Vec<LocalHitRecord> {
+        let step = RAY_EPSILON / ray.d.length();
+        let mut hits: Vec<LocalHitRecord> = Vec::new();
+        let mut t_start = 0.0;
+        while hits.len() < MAX_HITS {
+            let Some(mut hit) = self.intersect_local(&Ray::new(ray.at(&t_start), ray.d)) else {
+                break;
+            };
+            hit.t += t_start;
+            t_start = hit.t + step;
+            hits.push(hit);
+        }
+        hits
+    }
 }
 
-pub trait Sampleable {
-    /// Uniformly sample a position and normal on the surface using the samples passed
-    fn sample_uniform(&self, samples: &(f32, f32)) -> Vec3;
+/// Maximum number of hits `Shape::intersect_all_local` follows a ray
+/// through.
+const MAX_HITS: usize = 64;
+
+pub trait Sampleable: Shape {
+    /// Uniformly sample a position and normal on the surface using the samples passed.
+    /// The pdf of the returned sample is with respect to surface area, and
+    /// is given by `pdf_area` for shapes that do not sample uniformly.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample;
+
+    /// Density with respect to area of `sample_uniform` returning the point
+    /// `p` of the surface.
+    fn pdf_area(&self, _p: &Vec3) -> f64 {
+        1.0 / self.surface_area()
+    }
 
     /// Sample the object using the probability density of the solid angle
     /// from `p` to the sampled point on the surface.
-    /// Returns the sampled point and the surface normal at that point
-    fn sample(&self, p: &Vec3, samples: &(f32, f32)) -> Vec3;
+    /// Returns the sampled point and the surface normal at that point, a zero
+    /// pdf means no point could be sampled.
+    fn sample(&self, p: &Vec3, samples: &(f64, f64)) -> ShapeSample {
+        sample_by_area(self, p, samples)
+    }
 
     /// Return the surface area of the shape
-    fn surface_area(&self) -> f32;
+    fn surface_area(&self) -> f64;
 
     /// Compute the PDF that the ray from `p` with direction `w_i` intersects the shape
-    fn pdf(&self, p: &Vec3, w_i: &Vec3) -> f32;
+    fn pdf(&self, p: &Vec3, w_i: &Vec3) -> f64 {
+        pdf_by_area(self, p, w_i)
+    }
+}
+
+/// Sample `shape` uniformly by area and convert the density to solid angle
+/// as seen from `p`.
+pub fn sample_by_area<S: Sampleable + ?Sized>(
+    shape: &S,
+    p: &Vec3,
+    samples: &(f64, f64),
+) -> ShapeSample {
+    let mut sample = shape.sample_uniform(samples);
+    let d = sample.p - *p;
+    let dist_squared = d.length_squared();
+    if dist_squared == 0.0 {
+        sample.pdf = 0.0;
+        return sample;
+    }
+    let cos_theta = sample.n.dot(&d).abs() / dist_squared.sqrt();
+    sample.pdf = if cos_theta == 0.0 {
+        0.0
+    } else {
+        sample.pdf * dist_squared / cos_theta
+    };
+    sample
+}
+
+/// Solid angle density of [`sample_by_area`] for the ray from `p` with
+/// direction `w_i`.
+pub fn pdf_by_area<S: Sampleable + ?Sized>(shape: &S, p: &Vec3, w_i: &Vec3) -> f64 {
+    let Some(hit) = shape.intersect_local(&Ray::new(*p, *w_i)) else {
+        return 0.0;
+    };
+    let d = hit.p - *p;
+    let cos_theta = hit.n.dot(&d).abs() / d.length();
+    if cos_theta == 0.0 {
+        return 0.0;
+    }
+    shape.pdf_area(&hit.p) * d.length_squared() / cos_theta
+}
+
+/// The angle of `p` around the z axis in `[0, 2 pi)`, if it lies within a
+/// sweep from `phi = 0` to `phi_max`.
+pub(crate) fn sweep_phi(p: &Vec3, phi_max: f64) -> Option<f64> {
+    let mut phi = p.y.atan2(p.x);
+    if phi < 0.0 {
+        phi += 2.0 * std::f64::consts::PI;
+    }
+    (phi <= phi_max).then_some(phi)
 }
 
 pub trait Boundable {
     fn bounds(&self) -> AABB;
 }
 
-pub trait Geometry: Shape + Sampleable + Boundable {}
+pub trait Geometry: Shape + Sampleable + Boundable + Send + Sync {
+    /// The part of the shape whose hits have a material index accepted by
+    /// `filter`, so that parts with an emissive material can be lights of
+    /// their own. `None` if the shape cannot be split into parts.
+    fn material_part(&self, _filter: &dyn Fn(usize) -> bool) -> Option<Arc<dyn Geometry>> {
+        None
+    }
+}
+
+/// A point sampled on the surface of a shape.
+pub struct ShapeSample {
+    pub p: Vec3,
+    pub n: Vec3,
+    pub pdf: f64,
+}
 
 /// Local space hit record (before transformation)
 pub struct LocalHitRecord {
     pub t: f64,
     pub p: Vec3,
     pub n: Vec3,
+    /// Shading normal, `n` unless the shape interpolates vertex normals.
+    pub ns: Vec3,
+    /// Shading tangent, for shapes with per-vertex tangents.
+    pub tangent: Option<Vec3>,
     pub uv: (f64, f64),
+    /// Index of the material of the hit part of the shape among the
+    /// materials of its primitive.
+    pub material_index: usize,
 }
 
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     /// Time of the ray propagate (always positive).
     pub t: f64,
@@ -53,9 +174,47 @@ pub struct HitRecord<'a> {
     /// Surface normal at hit point.
     pub n: Vec3,
 
-    /// Material at the hit point.
+    /// Shading normal at hit point, which BSDFs are oriented around.
+    pub ns: Vec3,
+
+    /// Shading tangent at hit point, if the shape provides one.
+    pub tangent: Option<Vec3>,
+
+    /// The primitive hit.
     pub primitive: &'a Primitive,
 
+    /// Material at the hit point.
+    pub material: &'a Material,
+
+    /// The light emitted at the hit point, if its material is emissive.
+    pub area_light: Option<&'a AreaLight>,
+
     /// Texture coordinates.
     pub uv: (f64, f64),
 }
+
+/// Offset used to move ray origins off the surface they leave, so they do not
+/// hit it again.
+pub const RAY_EPSILON: f64 = 1e-6;
+
+impl HitRecord<'_> {
+    /// Spawn a ray leaving the hit point in direction `d`.
+    pub fn spawn_ray(&self, d: &Vec3) -> Ray {
+        Ray::new(offset_origin(&self.p, &self.n, d), *d)
+    }
+
+    /// Spawn a ray towards `p`. The ray reaches `p` at `t = 1`.
+    pub fn spawn_ray_to(&self, p: &Vec3) -> Ray {
+        let origin = offset_origin(&self.p, &self.n, &(*p - self.p));
+        Ray::new(origin, *p - origin)
+    }
+}
+
+/// Push `p` along `n` onto the side of the surface that `d` points into.
+pub fn offset_origin(p: &Vec3, n: &Vec3, d: &Vec3) -> Vec3 {
+    if n.dot(d) < 0.0 {
+        *p - RAY_EPSILON * *n
+    } else {
+        *p + RAY_EPSILON * *n
+    }
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quadratic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample, sweep_phi},
+};
+
+/// A canonical paraboloid `x^2 + y^2 = s z` around the z axis, of radius `r`
+/// at `z_max`, cut to `z_min <= z <= z_max` and swept from `phi = 0` to
+/// `phi_max`. Its normal faces away from the axis, out of the dish.
+pub struct Paraboloid {
+    r: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+}
+
+impl Paraboloid {
+    pub fn new(r: f64, z_min: f64, z_max: f64) -> Self {
+        let (z_min, z_max) = (z_min.min(z_max).max(0.0), z_min.max(z_max));
+        assert!(
+            r > 0.0 && z_max > 0.0,
+            "Paraboloid radius and height must be positive"
+        );
+        Self {
+            r,
+            z_min,
+            z_max,
+            phi_max: 2.0 * PI,
+        }
+    }
+
+    /// Only sweep the paraboloid up to the angle `phi_max`, in radians.
+    pub fn with_phi_max(mut self, phi_max: f64) -> Self {
+        self.phi_max = phi_max.clamp(0.0, 2.0 * PI);
+        self
+    }
+
+    /// The factor `s` of `x^2 + y^2 = s z`.
+    fn s(&self) -> f64 {
+        self.r * self.r / self.z_max
+    }
+
+    /// Outward normal at `p`, the gradient of `x^2 + y^2 - s z`.
+    fn normal(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(2.0 * p.x, 2.0 * p.y, -self.s()).normalize()
+    }
+
+    /// Antiderivative of the area element along z, up to a constant factor:
+    /// `rho sqrt(1 + rho'^2) = sqrt(s z + s^2 / 4)`.
+    fn area_integral(&self, z: f64) -> f64 {
+        let s = self.s();
+        (s * z + 0.25 * s * s).powf(1.5)
+    }
+}
+
+impl Shape for Paraboloid {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let s = self.s();
+        let (o, d) = (ray.p, ray.d);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2.0 * (d.x * o.x + d.y * o.y) - s * d.z;
+        let c = o.x * o.x + o.y * o.y - s * o.z;
+        let (t0, t1) = solve_quadratic(a, b, c)?;
+        [t0, t1]
+            .into_iter()
+            .filter(|&t| t > f64::EPSILON)
+            .find_map(|t| {
+                let p = ray.at(&t);
+                if p.z < self.z_min || p.z > self.z_max {
+                    return None;
+                }
+                let phi = sweep_phi(&p, self.phi_max)?;
+                let n = self.normal(&p);
+                Some(LocalHitRecord {
+                    t,
+                    p,
+                    n,
+                    ns: n,
+                    tangent: None,
+                    uv: (
+                        phi / self.phi_max,
+                        (p.z - self.z_min) / (self.z_max - self.z_min),
+                    ),
+                    material_index: 0,
+                })
+            })
+    }
+}
+
+impl Sampleable for Paraboloid {
+    /// Invert the area integral along z, then sample phi uniformly.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let s = self.s();
+        let (a0, a1) = (
+            self.area_integral(self.z_min),
+            self.area_integral(self.z_max),
+        );
+        let a = a0 + samples.0 * (a1 - a0);
+        let z = ((a.powf(2.0 / 3.0) - 0.25 * s * s) / s).clamp(self.z_min, self.z_max);
+        let rho = (s * z).sqrt();
+        let phi = samples.1 * self.phi_max;
+        let p = Vec3::new(rho * phi.cos(), rho * phi.sin(), z);
+        ShapeSample {
+            p,
+            n: self.normal(&p),
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        let integral = self.area_integral(self.z_max) - self.area_integral(self.z_min);
+        self.phi_max * 2.0 / (3.0 * self.s()) * integral
+    }
+}
+
+impl Boundable for Paraboloid {
+    fn bounds(&self) -> AABB {
+        AABB::new(
+            Vec3::new(-self.r, -self.r, self.z_min),
+            Vec3::new(self.r, self.r, self.z_max),
+        )
+    }
+}
+
+impl Geometry for Paraboloid {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paraboloid_area_and_samples() {
+        let paraboloid = Paraboloid::new(2.0, 0.5, 1.0);
+        // The closed form area of a paraboloid cap of radius a and height h
+        // is pi a / (6 h^2) ((a^2 + 4 h^2)^1.5 - a^3).
+        let cap =
+            |a: f64, h: f64| PI * a / (6.0 * h * h) * ((a * a + 4.0 * h * h).powf(1.5) - a.powi(3));
+        let area = cap(2.0, 1.0) - cap(2.0 * 0.5f64.sqrt(), 0.5);
+        assert!((paraboloid.surface_area() - area).abs() < 1e-9);
+
+        for i in 0..10 {
+            let sample = paraboloid.sample_uniform(&(i as f64 / 10.0, 0.3));
+            assert!((0.5..=1.0).contains(&sample.p.z));
+            let ray = Ray::new(sample.p + sample.n, -sample.n);
+            let hit = paraboloid.intersect_local(&ray).unwrap();
+            assert!((hit.p - sample.p).length() < 1e-9);
+        }
+    }
+}
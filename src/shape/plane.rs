@@ -0,0 +1,120 @@
+use crate::{
+    accel::aabb::AABB,
+    math::vec3::Vec3,
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// The infinite `z = 0` plane facing `+z`. Texture coordinates repeat every
+/// unit along `x` and `y`.
+///
+/// The plane has no finite area to sample, so as a light it is only found
+/// by the rays that hit it.
+#[derive(Default)]
+pub struct Plane {}
+
+impl Plane {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Shape for Plane {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        if ray.d.z == 0.0 {
+            return None;
+        }
+        let t = -ray.p.z / ray.d.z;
+        if t <= f64::EPSILON {
+            return None;
+        }
+        let mut p = ray.at(&t);
+        p.z = 0.0;
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        Some(LocalHitRecord {
+            t,
+            p,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (p.x.rem_euclid(1.0), p.y.rem_euclid(1.0)),
+            material_index: 0,
+        })
+    }
+}
+
+impl Sampleable for Plane {
+    /// No point can be sampled uniformly on an infinite plane, the sample
+    /// has a zero pdf.
+    fn sample_uniform(&self, _samples: &(f64, f64)) -> ShapeSample {
+        ShapeSample {
+            p: Vec3::zero(),
+            n: Vec3::new(0.0, 0.0, 1.0),
+            pdf: 0.0,
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        f64::INFINITY
+    }
+}
+
+impl Boundable for Plane {
+    fn bounds(&self) -> AABB {
+        AABB::new(
+            Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, 0.0),
+            Vec3::new(f64::INFINITY, f64::INFINITY, 0.0),
+        )
+    }
+}
+
+impl Geometry for Plane {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plane_intersection_normal_and_uv() {
+        let plane = Plane::new();
+
+        let ray = Ray::new(Vec3::new(2.25, -0.75, 4.0), Vec3::new(0.0, 0.0, -2.0));
+        let hit = plane.intersect_local(&ray).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert_eq!(hit.p, Vec3::new(2.25, -0.75, 0.0));
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(hit.uv, (0.25, 0.25));
+
+        // Far away and from below.
+        let ray = Ray::new(Vec3::new(1e6, 3.5, -1.0), Vec3::new(1.0, 0.0, 1.0));
+        let hit = plane.intersect_local(&ray).unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+        assert!(hit.uv.0.abs() < 1e-6 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        // Parallel and pointing away.
+        assert!(
+            plane
+                .intersect_local(&Ray::new(
+                    Vec3::new(0.0, 0.0, 1.0),
+                    Vec3::new(1.0, 1.0, 0.0)
+                ))
+                .is_none()
+        );
+        assert!(
+            plane
+                .intersect_local(&Ray::new(
+                    Vec3::new(0.0, 0.0, 1.0),
+                    Vec3::new(0.0, 0.1, 1.0)
+                ))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_plane_cannot_be_sampled() {
+        let plane = Plane::new();
+        assert_eq!(plane.sample_uniform(&(0.5, 0.5)).pdf, 0.0);
+        assert_eq!(plane.surface_area(), f64::INFINITY);
+    }
+}
@@ -1,10 +1,11 @@
-#![allow(unused_variables)]
-
 use std::sync::Arc;
 
 use crate::{
+    accel::aabb::AABB,
+    light::area::AreaLight,
     material::{Material, SurfaceInteraction},
-    math::transform::Transform,
+    math::{transform::Transform, vec3::Vec3},
+    medium::MediumInterface,
     render::ray::Ray,
     shape::{Geometry, HitRecord}
 };
@@ -18,6 +19,19 @@ pub struct Primitive {
 
     /// The matieral of the object.
     pub material: Arc<Material>,
+
+    /// Materials of the parts of a shape with several materials, such as
+    /// the faces of a mesh. `material` is used for the parts without one.
+    pub materials: Vec<Arc<Material>>,
+
+    /// The lights emitted by the parts of the primitive with an emissive
+    /// material, indexed by material slot. Set when the primitive is added
+    /// to a scene.
+    pub area_lights: Vec<Option<Arc<AreaLight>>>,
+
+    /// The media inside and outside the primitive, `None` if the primitive
+    /// does not change the medium rays travel through.
+    pub medium_interface: Option<MediumInterface>,
 }
 
 impl Primitive {
@@ -26,28 +40,89 @@ impl Primitive {
             shape,
             transform,
             material,
+            materials: Vec::new(),
+            area_lights: Vec::new(),
+            medium_interface: None,
+        }
+    }
+
+    pub fn with_materials(mut self, materials: Vec<Arc<Material>>) -> Self {
+        self.materials = materials;
+        self
+    }
+
+    /// The material of the part of the shape with material `index`.
+    pub fn material_at(&self, index: usize) -> &Material {
+        self.materials.get(index).unwrap_or(&self.material)
+    }
+
+    /// Every material of the primitive, `materials` followed by `material`,
+    /// such that `material_at(index)` is the material at
+    /// `material_slot(index)`.
+    pub(crate) fn material_table(&self) -> Vec<Arc<Material>> {
+        self.materials
+            .iter()
+            .chain([&self.material])
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn material_slot(&self, index: usize) -> usize {
+        index.min(self.materials.len())
+    }
+
+    /// The light emitted by the part of the shape with material `index`.
+    pub fn area_light_at(&self, index: usize) -> Option<&AreaLight> {
+        self.area_lights.get(self.material_slot(index))?.as_deref()
+    }
+
+    pub fn with_medium_interface(mut self, medium_interface: MediumInterface) -> Self {
+        self.medium_interface = Some(medium_interface);
+        self
+    }
+
+    /// World space bounds of the primitive. Unbounded shapes stay unbounded
+    /// in every direction, whatever their transform.
+    pub fn bounds(&self) -> AABB {
+        let bounds = self.shape.bounds();
+        if !bounds.is_finite() {
+            let infinity = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+            return AABB::new(-infinity, infinity);
         }
+        self.transform.apply_bounds(&bounds)
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Option<HitRecord> {
+    pub fn intersect(&self, ray: &Ray) -> Option<HitRecord<'_>> {
         let r = ray.apply_inv(&self.transform);
         self.shape.intersect_local(&r).map(|local_hit| {
             let p = ray.at(&local_hit.t);
-            let n = (self.transform.inv * local_hit.n.to_homo())
-                .to_inhomo()
-                .normalize();
+            let n = self.transform.apply_normal(&local_hit.n).normalize();
+            let ns = self.transform.apply_normal(&local_hit.ns).normalize();
+            let tangent = local_hit
+                .tangent
+                .map(|tangent| self.transform.apply_vector(&tangent).normalize());
 
             HitRecord {
                 t: local_hit.t,
                 p,
                 n,
+                ns,
+                tangent,
                 primitive: self,
+                material: self.material_at(local_hit.material_index),
+                area_light: self.area_light_at(local_hit.material_index),
                 uv: local_hit.uv,
             }
         })
     }
 
-    pub fn interact(&self, hit: &HitRecord) -> SurfaceInteraction {
-        self.material.interact(hit)
+    pub fn interact(
+        &self,
+        hit: &HitRecord,
+        wo: &Vec3,
+        u: f64,
+        samples: (f64, f64),
+    ) -> SurfaceInteraction {
+        hit.material.interact(hit, wo, u, samples)
     }
 }
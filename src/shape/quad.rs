@@ -0,0 +1,118 @@
+use crate::{
+    accel::aabb::AABB,
+    math::vec3::Vec3,
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// A parallelogram with a corner at `p` and the edges `u` and `v` leaving
+/// it. Its normal faces the side `u` and `v` are seen counterclockwise from.
+pub struct Quad {
+    p: Vec3,
+    u: Vec3,
+    v: Vec3,
+    /// Unnormalized normal `u x v`.
+    normal: Vec3,
+}
+
+impl Quad {
+    pub fn new(p: Vec3, u: Vec3, v: Vec3) -> Self {
+        let normal = u.cross(&v);
+        assert!(
+            normal.length_squared() > 0.0,
+            "Quad edges must not be parallel"
+        );
+        Self { p, u, v, normal }
+    }
+
+    /// A `width` by `height` rectangle centred at the origin in the `z = 0`
+    /// plane, facing `+z`.
+    pub fn rectangle(width: f64, height: f64) -> Self {
+        Self::new(
+            Vec3::new(-0.5 * width, -0.5 * height, 0.0),
+            Vec3::new(width, 0.0, 0.0),
+            Vec3::new(0.0, height, 0.0),
+        )
+    }
+}
+
+impl Shape for Quad {
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let denom = self.normal.dot(&ray.d);
+        if denom == 0.0 {
+            return None;
+        }
+        let t = self.normal.dot(&(self.p - ray.p)) / denom;
+        if t <= f64::EPSILON {
+            return None;
+        }
+        // Coordinates of the hit point along the edges.
+        let q = ray.at(&t) - self.p;
+        let w = self.normal / self.normal.length_squared();
+        let a = w.dot(&q.cross(&self.v));
+        let b = w.dot(&self.u.cross(&q));
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return None;
+        }
+        let n = self.normal.normalize();
+        Some(LocalHitRecord {
+            t,
+            p: self.p + a * self.u + b * self.v,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (a, b),
+            material_index: 0,
+        })
+    }
+}
+
+impl Sampleable for Quad {
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        ShapeSample {
+            p: self.p + samples.0 * self.u + samples.1 * self.v,
+            n: self.normal.normalize(),
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        self.normal.length()
+    }
+}
+
+impl Boundable for Quad {
+    fn bounds(&self) -> AABB {
+        AABB::new(self.p, self.p + self.u + self.v)
+            .union_point(&(self.p + self.u))
+            .union_point(&(self.p + self.v))
+    }
+}
+
+impl Geometry for Quad {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quad_intersection_and_uv() {
+        // A skewed parallelogram in the z = 1 plane.
+        let quad = Quad::new(
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+        );
+        assert_eq!(quad.surface_area(), 2.0);
+
+        let ray = Ray::new(Vec3::new(2.0, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = quad.intersect_local(&ray).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-12);
+        assert_eq!(hit.n, Vec3::new(0.0, 0.0, 1.0));
+        assert!((hit.uv.0 - 0.75).abs() < 1e-12 && (hit.uv.1 - 0.5).abs() < 1e-12);
+
+        // Inside the bounds but outside the parallelogram.
+        let ray = Ray::new(Vec3::new(0.2, 0.9, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(quad.intersect_local(&ray).is_none());
+    }
+}
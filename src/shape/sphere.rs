@@ -1,6 +1,12 @@
+use std::f64::consts::PI;
+
 use crate::{
+    accel::aabb::AABB,
+    math::{sampling::uniform_sample_sphere, vec3::Vec3},
     render::ray::Ray,
-    shape::{Boundable, LocalHitRecord, Sampleable, Shape},
+    shape::{
+        Boundable, LocalHitRecord, Sampleable, Shape, ShapeSample, pdf_by_area, sample_by_area,
+    },
 };
 
 use super::Geometry;
@@ -52,32 +58,82 @@ impl Shape for Sphere {
             t,
             p,
             n,
+            ns: n,
+            tangent: None,
             uv: (u, v),
+            material_index: 0,
         })
     }
 }
 
 impl Sampleable for Sphere {
-    fn pdf(&self, p: &crate::math::vec3::Vec3, w_i: &crate::math::vec3::Vec3) -> f32 {
-        todo!()
+    fn pdf(&self, p: &Vec3, w_i: &Vec3) -> f64 {
+        let dist_squared = p.length_squared();
+        if dist_squared <= self.r * self.r {
+            return pdf_by_area(self, p, w_i);
+        }
+        if self.intersect_local(&Ray::new(*p, *w_i)).is_none() {
+            return 0.0;
+        }
+        let cos_theta_max = (1.0 - self.r * self.r / dist_squared).max(0.0).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
     }
 
-    fn sample(&self, p: &crate::math::vec3::Vec3, samples: &(f32, f32)) -> crate::math::vec3::Vec3 {
-        todo!()
+    /// Sample the cone of directions subtended by the sphere when `p` lies
+    /// outside of it, otherwise fall back to sampling by area.
+    fn sample(&self, p: &Vec3, samples: &(f64, f64)) -> ShapeSample {
+        let dist_squared = p.length_squared();
+        if dist_squared <= self.r * self.r {
+            return sample_by_area(self, p, samples);
+        }
+
+        let dist = dist_squared.sqrt();
+        let sin_theta_max_squared = self.r * self.r / dist_squared;
+        let cos_theta_max = (1.0 - sin_theta_max_squared).max(0.0).sqrt();
+        let cos_theta = (1.0 - samples.0) + samples.0 * cos_theta_max;
+        let sin_theta_squared = (1.0 - cos_theta * cos_theta).max(0.0);
+        let phi = samples.1 * 2.0 * PI;
+
+        // Angle between the direction to the centre and the normal of the
+        // sampled point, seen from the centre of the sphere.
+        let cos_alpha = sin_theta_squared / sin_theta_max_squared.sqrt()
+            + cos_theta
+                * (1.0 - sin_theta_squared / sin_theta_max_squared)
+                    .max(0.0)
+                    .sqrt();
+        let sin_alpha = (1.0 - cos_alpha * cos_alpha).max(0.0).sqrt();
+
+        // Build the normal in a frame whose z axis points from the centre to `p`.
+        let wc = *p / dist;
+        let local = Vec3::new(sin_alpha * phi.cos(), sin_alpha * phi.sin(), cos_alpha);
+        let n = wc.from_local(&local);
+        ShapeSample {
+            p: self.r * n,
+            n,
+            pdf: 1.0 / (2.0 * PI * (1.0 - cos_theta_max)),
+        }
     }
 
-    fn sample_uniform(&self, samples: &(f32, f32)) -> crate::math::vec3::Vec3 {
-        todo!()
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let n = uniform_sample_sphere(*samples);
+        ShapeSample {
+            p: self.r * n,
+            n,
+            pdf: 1.0 / self.surface_area(),
+        }
     }
 
-    fn surface_area(&self) -> f32 {
-        todo!()
+    fn surface_area(&self) -> f64 {
+        4.0 * PI * self.r * self.r
     }
 }
 
 impl Boundable for Sphere {
-    fn bounds(&self) -> crate::accel::aabb::AABB {
-        todo!()
+    fn bounds(&self) -> AABB {
+        AABB::new(
+            Vec3::new(-self.r, -self.r, -self.r),
+            Vec3::new(self.r, self.r, self.r),
+        )
     }
 }
 
@@ -0,0 +1,176 @@
+use std::f64::consts::PI;
+
+use crate::{
+    accel::aabb::AABB,
+    math::{roots::solve_quartic, vec3::Vec3},
+    render::ray::Ray,
+    shape::{Boundable, Geometry, LocalHitRecord, Sampleable, Shape, ShapeSample},
+};
+
+/// Newton iterations inverting the area distribution when sampling.
+const SAMPLE_ITERATIONS: usize = 32;
+
+/// A canonical ring torus around the z axis: a tube of radius `r` whose
+/// centre follows the circle of radius `big_r` in the `z = 0` plane.
+pub struct Torus {
+    big_r: f64,
+    r: f64,
+}
+
+impl Torus {
+    pub fn new(big_r: f64, r: f64) -> Self {
+        assert!(0.0 < r && r < big_r, "Torus radii must satisfy 0 < r < R");
+        Self { big_r, r }
+    }
+
+    /// The point at the angle `phi` around the z axis and `theta` around the
+    /// tube, with its normal.
+    fn point(&self, phi: f64, theta: f64) -> (Vec3, Vec3) {
+        let n = Vec3::new(
+            theta.cos() * phi.cos(),
+            theta.cos() * phi.sin(),
+            theta.sin(),
+        );
+        let centre = Vec3::new(self.big_r * phi.cos(), self.big_r * phi.sin(), 0.0);
+        (centre + self.r * n, n)
+    }
+}
+
+impl Shape for Torus {
+    /// Solve `(|p|^2 - R^2 - r^2)^2 + 4 R^2 (z^2 - r^2) = 0` along the ray.
+    /// The ray is normalized and its origin moved to where it enters the
+    /// bounds first, which keeps the coefficients of the quartic small.
+    fn intersect_local(&self, ray: &Ray) -> Option<LocalHitRecord> {
+        let length = ray.d.length();
+        let d = ray.d / length;
+        let (t_enter, t_exit) = self
+            .bounds()
+            .intersect(&Ray::new(ray.p, d), f64::INFINITY)?;
+        let o = ray.p + t_enter * d;
+
+        let big_r2 = self.big_r * self.big_r;
+        let f = o.dot(&d);
+        let e = o.dot(&o) - big_r2 - self.r * self.r;
+        let roots = solve_quartic(
+            1.0,
+            4.0 * f,
+            4.0 * f * f + 2.0 * e + 4.0 * big_r2 * d.z * d.z,
+            4.0 * f * e + 8.0 * big_r2 * o.z * d.z,
+            e * e + 4.0 * big_r2 * (o.z * o.z - self.r * self.r),
+        );
+        let t = roots
+            .into_iter()
+            .map(|t| t + t_enter)
+            .find(|&t| t / length > f64::EPSILON && t <= t_exit + self.r * 1e-6)?;
+
+        let p = ray.at(&(t / length));
+        let phi = p.y.atan2(p.x);
+        let centre = Vec3::new(self.big_r * phi.cos(), self.big_r * phi.sin(), 0.0);
+        let n = (p - centre).normalize();
+        let theta = n.z.atan2((p.x * p.x + p.y * p.y).sqrt() - self.big_r);
+        Some(LocalHitRecord {
+            t: t / length,
+            p,
+            n,
+            ns: n,
+            tangent: None,
+            uv: (
+                phi.rem_euclid(2.0 * PI) / (2.0 * PI),
+                theta.rem_euclid(2.0 * PI) / (2.0 * PI),
+            ),
+            material_index: 0,
+        })
+    }
+}
+
+impl Sampleable for Torus {
+    /// The area element is proportional to `R + r cos(theta)`, whose
+    /// distribution is inverted by safeguarded Newton iterations.
+    fn sample_uniform(&self, samples: &(f64, f64)) -> ShapeSample {
+        let target = samples.0 * 2.0 * PI * self.big_r;
+        let (mut lo, mut hi) = (0.0, 2.0 * PI);
+        let mut theta = samples.0 * 2.0 * PI;
+        for _ in 0..SAMPLE_ITERATIONS {
+            let f = self.big_r * theta + self.r * theta.sin() - target;
+            if f.abs() < 1e-12 {
+                break;
+            }
+            if f < 0.0 {
+                lo = theta;
+            } else {
+                hi = theta;
+            }
+            theta -= f / (self.big_r + self.r * theta.cos());
+            if !(lo..=hi).contains(&theta) {
+                theta = 0.5 * (lo + hi);
+            }
+        }
+        let (p, n) = self.point(samples.1 * 2.0 * PI, theta);
+        ShapeSample {
+            p,
+            n,
+            pdf: 1.0 / self.surface_area(),
+        }
+    }
+
+    fn surface_area(&self) -> f64 {
+        4.0 * PI * PI * self.big_r * self.r
+    }
+}
+
+impl Boundable for Torus {
+    fn bounds(&self) -> AABB {
+        let extent = self.big_r + self.r;
+        AABB::new(
+            Vec3::new(-extent, -extent, -self.r),
+            Vec3::new(extent, extent, self.r),
+        )
+    }
+}
+
+impl Geometry for Torus {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_torus_intersection() {
+        let torus = Torus::new(2.0, 0.5);
+        // Through the hole, hitting the inner side of the tube.
+        let ray = Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+        let hit = torus.intersect_local(&ray).unwrap();
+        assert!((hit.t - 3.75).abs() < 1e-9);
+        assert!((hit.n - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+        // From the inside of the tube.
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = torus.intersect_local(&ray).unwrap();
+        assert!((hit.p - Vec3::new(2.0, 0.0, 0.5)).length() < 1e-9);
+        // Straight down the hole, and grazing past the top.
+        assert!(
+            torus
+                .intersect_local(&Ray::new(
+                    Vec3::new(0.0, 0.0, 5.0),
+                    Vec3::new(0.0, 0.0, -1.0)
+                ))
+                .is_none()
+        );
+        assert!(
+            torus
+                .intersect_local(&Ray::new(
+                    Vec3::new(-5.0, 0.0, 0.51),
+                    Vec3::new(1.0, 0.0, 0.0)
+                ))
+                .is_none()
+        );
+
+        // Samples lie on the surface.
+        for i in 0..10 {
+            let sample = torus.sample_uniform(&(i as f64 / 10.0, 0.2));
+            let hit = torus
+                .intersect_local(&Ray::new(sample.p + sample.n, -sample.n))
+                .unwrap();
+            assert!((hit.p - sample.p).length() < 1e-9);
+        }
+    }
+}